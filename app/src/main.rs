@@ -1,7 +1,8 @@
-use pathfinding::PathfindingResult;
-use eframe::egui::{Context, Sense, StrokeKind};
+use pathfinding::{EnergyBudget, Footprint, Harvest, Hopper, MoveCosts, Neighborhood, PathfindingEvent, PathfindingResult, Planner, PlanningOptions, Position, Revisits, Scenario, Simulation, TimestepSemantics};
+use eframe::egui::{Context, Sense, StrokeKind, UiBuilder};
 use eframe::{egui, Frame};
 use pathfinding::Graph;
+use std::collections::{HashMap, HashSet};
 use std::sync::{mpsc, Arc, Mutex};
 use std::thread;
 use std::time::Duration;
@@ -31,7 +32,7 @@ fn main() {
             .start(
                 canvas,
                 web_options,
-                Box::new(|cc| Ok(Box::new(MyApp::build()))),
+                Box::new(|_cc| Ok(Box::new(MyApp::build()))),
             )
             .await
             .expect("failed to start app");
@@ -43,17 +44,27 @@ fn main() {
     eframe::run_native(
         "pathfinding",
         eframe::NativeOptions::default(),
-        Box::new(|ctx| Ok(Box::new(MyApp::build())))
+        Box::new(|_ctx| Ok(Box::new(MyApp::build())))
     )
         .expect("failed to initialise app")
 }
 
 const WIDGET_SPACING: f32 = 10.0;
 
-#[derive(Debug, PartialEq, Clone)]
-enum PathfindingStrategy {
-    BestFirstSearch,
-    DepthFirstSearch
+/// A single frame of a recorded search, replayed when animating the search.
+#[derive(Clone)]
+enum SearchFrame {
+    Expanded(Position),
+    Incumbent(PathfindingResult)
+}
+
+/// Records the events of a planner so the search can be replayed afterwards.
+fn record_search(trace: &mut Vec<SearchFrame>) -> impl FnMut(PathfindingEvent<'_>) + '_ {
+    move |event| match event {
+        PathfindingEvent::NodeExpanded { node, .. } => trace.push(SearchFrame::Expanded(node)),
+        PathfindingEvent::IncumbentImproved { path, .. } => trace.push(SearchFrame::Incumbent(PathfindingResult { path: path.to_vec() })),
+        PathfindingEvent::IterationFinished { .. } => {}
+    }
 }
 
 pub struct MyApp {
@@ -63,9 +74,35 @@ pub struct MyApp {
     timesteps: u32,
     max_milliseconds: u64,
     recovery_rate: u32,
-    strategy: PathfindingStrategy,
+    seed: u64,
+    neighborhood: Neighborhood,
+    /// Kept from an opened scenario, there are no controls for it.
+    semantics: TimestepSemantics,
+    /// Kept from an opened scenario, there are no controls for it.
+    move_costs: MoveCosts,
+    /// Kept from an opened scenario, there are no controls for it.
+    footprint: Footprint,
+    /// Kept from an opened scenario, there are no controls for it.
+    harvest: Harvest,
+    /// Kept from an opened scenario together with its chargers, there are no controls for it.
+    energy: Option<EnergyBudget>,
+    /// Kept from an opened scenario together with its depots, there are no controls for it.
+    hopper: Option<Hopper>,
+    strategy: Planner,
     path: Arc<Mutex<PathfindingResult>>,
-    start: Option<(usize, usize)>
+    start: Option<(usize, usize)>,
+    /// The cells the path has to pass through in order, placed by clicking while `placing_waypoints` is on.
+    waypoints: Vec<Position>,
+    placing_waypoints: bool,
+    revisits: Revisits,
+    animate: bool,
+    frames_per_second: u32,
+    trace: Arc<Mutex<Vec<SearchFrame>>>,
+    playback_started: Option<f64>,
+    run_options: PlanningOptions,
+    playback_step: usize,
+    loaded_scenario: Arc<Mutex<Option<Scenario>>>,
+    status: Arc<Mutex<String>>
 }
 
 impl MyApp {
@@ -77,9 +114,162 @@ impl MyApp {
             timesteps: 10,
             max_milliseconds: 1000,
             recovery_rate: 1,
-            strategy: PathfindingStrategy::BestFirstSearch,
+            seed: 0,
+            neighborhood: Neighborhood::Moore,
+            semantics: TimestepSemantics::default(),
+            move_costs: MoveCosts::default(),
+            footprint: Footprint::Cell,
+            harvest: Harvest::Full,
+            energy: None,
+            hopper: None,
+            strategy: Planner::BestFirstSearch,
             path: Arc::new(Mutex::new(PathfindingResult::empty())),
-            start: None
+            start: None,
+            waypoints: Vec::new(),
+            placing_waypoints: false,
+            revisits: Revisits::default(),
+            animate: true,
+            frames_per_second: 20,
+            trace: Arc::new(Mutex::new(Vec::new())),
+            playback_started: None,
+            run_options: PlanningOptions::new(10, 1),
+            playback_step: 0,
+            loaded_scenario: Arc::new(Mutex::new(None)),
+            status: Arc::new(Mutex::new(String::new()))
+        }
+    }
+
+    /// Returns the planning options of the current settings.
+    fn options(&self) -> PlanningOptions {
+        PlanningOptions {
+            seed: self.seed,
+            neighborhood: self.neighborhood,
+            semantics: self.semantics,
+            move_costs: self.move_costs,
+            footprint: self.footprint,
+            harvest: self.harvest,
+            energy: self.energy,
+            hopper: self.hopper,
+            waypoints: self.waypoints.clone(),
+            revisits: self.revisits,
+            ..PlanningOptions::new(self.timesteps, self.recovery_rate)
+        }
+    }
+
+    /// Returns the current grid and settings as a scenario with an inline grid.
+    fn scenario(&self) -> Scenario {
+        let graph = self.graph.lock().expect("Failed to obtain mutex for graph");
+        Scenario::inline(&graph, self.start.into_iter().collect(), self.strategy.clone(), self.options())
+    }
+
+    /// Applies the grid and settings of a scenario which was opened, only the first start is used.
+    fn apply_scenario(&mut self, scenario: Scenario, base_dir: &std::path::Path) {
+        let graph = match scenario.graph(base_dir) {
+            Ok(graph) => graph,
+            Err(error) => {
+                *self.status.lock().expect("Failed to obtain mutex for status") = format!("Unable to load grid: {error}");
+                return;
+            }
+        };
+
+        *self.graph.lock().expect("Failed to obtain mutex for graph") = graph;
+        *self.path.lock().expect("Failed to obtain mutex for path") = PathfindingResult::empty();
+        self.status.lock().expect("Failed to obtain mutex for status").clear();
+        self.start = scenario.starts.first().copied();
+        self.timesteps = scenario.options.max_timesteps;
+        self.recovery_rate = scenario.options.recovery_rate;
+        self.seed = scenario.options.seed;
+        self.neighborhood = scenario.options.neighborhood;
+        self.semantics = scenario.options.semantics;
+        self.move_costs = scenario.options.move_costs;
+        self.footprint = scenario.options.footprint;
+        self.harvest = scenario.options.harvest;
+        self.energy = scenario.options.energy;
+        self.hopper = scenario.options.hopper;
+        self.waypoints = scenario.options.waypoints;
+        self.revisits = scenario.options.revisits;
+        self.strategy = scenario.planner;
+        self.playback_started = None;
+        self.playback_step = 0;
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn open_scenario(&self) {
+        let loaded = Arc::clone(&self.loaded_scenario);
+        let status = Arc::clone(&self.status);
+
+        let future = async move {
+            let file = rfd::AsyncFileDialog::new()
+                .add_filter("scenario", &["toml", "json"])
+                .pick_file()
+                .await;
+
+            if let Some(file) = file {
+                let bytes = file.read().await;
+                let format = pathfinding::ScenarioFormat::from_path(std::path::Path::new(&file.file_name()));
+                let contents = String::from_utf8_lossy(&bytes);
+
+                match Scenario::parse(&contents, format) {
+                    Ok(scenario) => *loaded.lock().expect("Failed to obtain mutex for scenario") = Some(scenario),
+                    Err(error) => *status.lock().expect("Failed to obtain mutex for status") = format!("Unable to open scenario: {error}")
+                }
+            }
+        };
+
+        wasm_bindgen_futures::spawn_local(future);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn open_scenario(&mut self) {
+        let file = rfd::FileDialog::new()
+            .add_filter("scenario", &["toml", "json"])
+            .pick_file();
+
+        if let Some(file) = file {
+            match Scenario::from_file(&file) {
+                Ok(scenario) => self.apply_scenario(scenario, file.parent().unwrap_or(std::path::Path::new("."))),
+                Err(error) => *self.status.lock().expect("Failed to obtain mutex for status") = format!("Unable to open scenario: {error}")
+            }
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn save_scenario(&self) {
+        let scenario = self.scenario();
+        let status = Arc::clone(&self.status);
+
+        let future = async move {
+            let file = rfd::AsyncFileDialog::new()
+                .add_filter("scenario", &["toml", "json"])
+                .set_file_name("scenario.toml")
+                .save_file()
+                .await;
+
+            if let Some(file) = file {
+                let format = pathfinding::ScenarioFormat::from_path(std::path::Path::new(&file.file_name()));
+                let result = match scenario.to_string(format) {
+                    Ok(contents) => file.write(contents.as_bytes()).await.map_err(|e| e.to_string()),
+                    Err(error) => Err(error.to_string())
+                };
+
+                if let Err(error) = result {
+                    *status.lock().expect("Failed to obtain mutex for status") = format!("Unable to save scenario: {error}");
+                }
+            }
+        };
+
+        wasm_bindgen_futures::spawn_local(future);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save_scenario(&self) {
+        let file = rfd::FileDialog::new()
+            .add_filter("scenario", &["toml", "json"])
+            .set_file_name("scenario.toml")
+            .save_file();
+
+        if let Some(file) = file && let Err(error) = self.scenario().save(&file) {
+            *self.status.lock().expect("Failed to obtain mutex for status") = format!("Unable to save scenario: {error}");
         }
     }
 
@@ -87,10 +277,11 @@ impl MyApp {
     pub fn upload_file(&self) {
         let graph = Arc::clone(&self.graph);
         let path = Arc::clone(&self.path);
+        let status = Arc::clone(&self.status);
 
         let future = async move {
             let file = rfd::AsyncFileDialog::new()
-                .add_filter("text", &["txt"])
+                .add_filter("grid", &["txt", "csv", "tsv", "grid", "asc", "pgm", "png"])
                 .pick_file()
                 .await;
 
@@ -99,8 +290,13 @@ impl MyApp {
 
             if let Some(file) = file {
                 let bytes = file.read().await;
-                *graph_ = Graph::from_bytes(bytes);
-                *path_ = PathfindingResult::empty();
+                match Graph::decode(&bytes, &pathfinding::ImageOptions::default()) {
+                    Ok(graph) => {
+                        *graph_ = graph;
+                        *path_ = PathfindingResult::empty();
+                    }
+                    Err(error) => *status.lock().expect("Failed to obtain mutex for status") = format!("Unable to open grid: {error}")
+                }
             }
         };
 
@@ -110,7 +306,7 @@ impl MyApp {
     #[cfg(not(target_arch = "wasm32"))]
     pub fn upload_file(&self) {
         let file = rfd::FileDialog::new()
-            .add_filter("text", &["txt"])
+            .add_filter("grid", &["txt", "csv", "tsv", "grid", "asc", "pgm", "png"])
             .pick_file();
 
         let path = Arc::clone(&self.path);
@@ -121,14 +317,18 @@ impl MyApp {
             let mut path_ = path.lock().expect("Failed to obtain mutex for path");
             let mut graph_ = graph.lock().expect("Failed to obtain mutex for graph");
 
-            let file_path = file.as_path();
-            *graph_ = Graph::from_file(file_path);
-            *path_ = PathfindingResult::empty();
+            match Graph::open(&file) {
+                Ok(graph) => {
+                    *graph_ = graph;
+                    *path_ = PathfindingResult::empty();
+                }
+                Err(error) => *self.status.lock().expect("Failed to obtain mutex for status") = format!("Unable to open grid: {error}")
+            }
         }
     }
 
     #[cfg(not(target_arch = "wasm32"))]
-    pub fn find_path(&self) {
+    pub fn find_path(&mut self, now: f64) {
         let origin = match self.start {
             Some((x, y)) => (x, y),
             None => (0, 0)
@@ -138,63 +338,162 @@ impl MyApp {
 
         let (tx, rx) = mpsc::channel();
 
-        let timesteps = self.timesteps;
-        let recovery_rate = self.recovery_rate;
+        let options = self.options();
         let strategy = self.strategy.clone();
         let graph_ = Arc::clone(&self.graph);
 
+        self.run_options = options.clone();
+
         thread::spawn(move || {
-            let result = match strategy {
-                PathfindingStrategy::BestFirstSearch => graph_.lock().expect("Failed to obtain mutex for graph").path_planning_bfs(origin, timesteps, recovery_rate),
-                PathfindingStrategy::DepthFirstSearch => graph_.lock().expect("Failed to obtain mutex for graph").path_planning_dfs(origin, timesteps, recovery_rate)
-            };
-            let _ = tx.send(result); // Send result through the channel
+            let mut trace = Vec::new();
+            let result = strategy.plan(&graph_.lock().expect("Failed to obtain mutex for graph"), origin, &options, &mut record_search(&mut trace));
+            let _ = tx.send((result, trace)); // Send result through the channel
         });
 
-        let path_found = match rx.recv_timeout(timeout) {
-            Ok(path) => path,
-            _ => PathfindingResult::empty()
+        let (path_found, trace) = match rx.recv_timeout(timeout) {
+            Ok(found) => found,
+            _ => (PathfindingResult::empty(), Vec::new())
         };
 
-        let mut path_ = self.path.lock().expect("Failed to obtain mutex for path");
-
-        *path_ = path_found;
+        self.show_result(path_found, trace, now);
     }
 
     #[cfg(target_arch = "wasm32")]
-    pub fn find_path(&self) {
+    pub fn find_path(&mut self, now: f64) {
         let origin = match self.start {
             Some((x, y)) => (x, y),
             None => (0, 0)
         };
 
-        let timesteps = self.timesteps;
-        let recovery_rate = self.recovery_rate;
+        let options = self.options();
         let strategy = self.strategy.clone();
         let graph_ = Arc::clone(&self.graph);
 
-        let result = match strategy {
-            PathfindingStrategy::BestFirstSearch => graph_.lock().expect("Failed to obtain mutex for graph").path_planning_bfs(origin, timesteps, recovery_rate),
-            PathfindingStrategy::DepthFirstSearch => graph_.lock().expect("Failed to obtain mutex for graph").path_planning_dfs(origin, timesteps, recovery_rate)
-        };
+        self.run_options = options.clone();
+        let mut trace = Vec::new();
 
+        let result = strategy.plan(&graph_.lock().expect("Failed to obtain mutex for graph"), origin, &options, &mut record_search(&mut trace));
+
+        self.show_result(result, trace, now);
+    }
+
+    /// Shows the path found by a planner, replaying the recorded search first when animating.
+    fn show_result(&mut self, result: PathfindingResult, trace: Vec<SearchFrame>, now: f64) {
         let mut path_ = self.path.lock().expect("Failed to obtain mutex for path");
+        let mut trace_ = self.trace.lock().expect("Failed to obtain mutex for trace");
 
+        self.playback_step = result.path.len();
         *path_ = result;
+        *trace_ = trace;
+        self.playback_started = if self.animate && !trace_.is_empty() { Some(now) } else { None };
+    }
+
+    /// Returns the search frames which should be visible at the given time, or `None` when the
+    /// playback has finished and the final path should be shown.
+    fn playback_frames(&mut self, now: f64) -> Option<Vec<SearchFrame>> {
+        let started = self.playback_started?;
+        let trace = self.trace.lock().expect("Failed to obtain mutex for trace");
+        let visible = ((now - started) * self.frames_per_second as f64) as usize + 1;
+
+        if visible >= trace.len() {
+            drop(trace);
+            self.playback_started = None;
+            return None;
+        }
+
+        Some(trace[..visible].to_vec())
     }
 }
 
 impl eframe::App for MyApp {
 
-    fn update(&mut self, ctx: &Context, frame: &mut Frame) {
+    fn update(&mut self, ctx: &Context, _frame: &mut Frame) {
+
+        // Scenarios opened in the browser arrive asynchronously, grids are resolved relative to the working directory
+        let loaded_scenario = self.loaded_scenario.lock().expect("Failed to obtain mutex for scenario").take();
+        if let Some(scenario) = loaded_scenario {
+            self.apply_scenario(scenario, std::path::Path::new("."));
+        }
 
-        let path = Arc::clone(&self.path);
         let graph = Arc::clone(&self.graph);
+        let now = ctx.input(|i| i.time);
+
+        // While the search is replayed, show the expanded nodes and the incumbent path of the
+        // frames played so far instead of the final path
+        let (path, expanded) = match self.playback_frames(now) {
+            Some(frames) => {
+                ctx.request_repaint();
+
+                let mut incumbent = PathfindingResult::empty();
+                let mut expanded = HashSet::new();
+
+                for frame in frames {
+                    match frame {
+                        SearchFrame::Expanded(node) => { expanded.insert(node); }
+                        SearchFrame::Incumbent(path) => incumbent = path
+                    }
+                }
+
+                (incumbent, expanded)
+            }
+            None => {
+                let path = self.path.lock().expect("Failed to obtain mutex for path");
+                let steps = path.path.iter().take(self.playback_step).copied().collect();
+                (PathfindingResult { path: steps }, HashSet::new())
+            }
+        };
+
+        // The scores of the grid after the steps of the shown path, as the agent sees them, the
+        // cells the agent collected at its last step with the fraction of their score, and the
+        // number of waypoints it passed
+        let (score_grid, footprint, reached) = {
+            let graph = graph.lock().expect("Failed to obtain mutex for graph");
+            let mut simulation = Simulation::new(&graph, &self.run_options);
+
+            for step in &path.path {
+                if simulation.step(step.node).is_err() {
+                    break;
+                }
+            }
+
+            let footprint: HashMap<Position, f64> = match path.path.last() {
+                Some(step) if self.run_options.footprint != Footprint::Cell => simulation.footprint_around(step.node).collect(),
+                _ => HashMap::new()
+            };
+            (simulation.score_grid(), footprint, simulation.reached_waypoints())
+        };
+        let agent = path.path.last().map(|s| s.node);
+        let (path_len, elapsed) = {
+            let path = self.path.lock().expect("Failed to obtain mutex for path");
+            (path.path.len(), path.path.last().map_or(0, |s| s.step))
+        };
 
         egui::SidePanel::right("my_left_panel").show(ctx, |ui| {
             ui.with_layout(egui::Layout::top_down_justified(egui::Align::Center), |ui| {
                 ui.add_space(WIDGET_SPACING);
-                ui.label(format!("Score: {}", path.lock().expect("Failed to obtain mutex for path").score()));
+                ui.label(format!("Score: {}", path.score()));
+
+                // The battery after the last step of the shown path
+                if let Some(budget) = self.run_options.energy {
+                    let energy = path.path.last().and_then(|s| s.energy).unwrap_or(budget.capacity);
+                    ui.add(
+                        egui::ProgressBar::new(energy as f32 / budget.capacity.max(1) as f32)
+                            .text(format!("Energy: {energy}/{}", budget.capacity)),
+                    );
+                }
+
+                // The load carried after the last step of the shown path
+                if let Some(hopper) = self.run_options.hopper {
+                    let load = path.path.last().and_then(|s| s.load).unwrap_or(0);
+                    ui.add(
+                        egui::ProgressBar::new(load as f32 / hopper.capacity.max(1) as f32)
+                            .text(format!("Load: {load}/{}", hopper.capacity)),
+                    );
+                }
+
+                if !self.run_options.waypoints.is_empty() {
+                    ui.label(format!("Waypoints: {reached}/{}", self.run_options.waypoints.len()));
+                }
 
                 ui.add_space(WIDGET_SPACING);
                 ui.label("SETTINGS");
@@ -203,8 +502,15 @@ impl eframe::App for MyApp {
                 egui::ComboBox::from_label("Strategy")
                     .selected_text(format!("{:?}", self.strategy))
                     .show_ui(ui, |ui| {
-                        ui.selectable_value(&mut self.strategy, PathfindingStrategy::BestFirstSearch, "Breadth first search");
-                        ui.selectable_value(&mut self.strategy, PathfindingStrategy::DepthFirstSearch, "Depth first search");
+                        ui.selectable_value(&mut self.strategy, Planner::BestFirstSearch, "Best first search");
+                        ui.selectable_value(&mut self.strategy, Planner::DepthFirstSearch, "Depth first search (exact)");
+                    });
+
+                egui::ComboBox::from_label("Neighborhood")
+                    .selected_text(format!("{:?}", self.neighborhood))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut self.neighborhood, Neighborhood::Moore, "Moore (8 neighbors)");
+                        ui.selectable_value(&mut self.neighborhood, Neighborhood::VonNeumann, "Von Neumann (4 neighbors)");
                     });
 
                 ui.add(
@@ -225,23 +531,85 @@ impl eframe::App for MyApp {
                         .integer(),
                 );
 
+                ui.horizontal(|ui| {
+                    ui.add(egui::DragValue::new(&mut self.seed));
+                    ui.label("Seed");
+                });
+
+                // Without the checkbox a cell may be entered again any number of times
+                ui.horizontal(|ui| {
+                    let mut limited = self.revisits.limit.is_some();
+                    ui.checkbox(&mut limited, "Max revisits");
+                    let mut limit = self.revisits.limit.unwrap_or(1);
+                    ui.add_enabled(limited, egui::DragValue::new(&mut limit));
+                    self.revisits.limit = limited.then_some(limit);
+                });
+
+                ui.horizontal(|ui| {
+                    ui.add(egui::DragValue::new(&mut self.revisits.cooldown));
+                    ui.label("Cooldown in timesteps");
+                });
+
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.placing_waypoints, "Place waypoints");
+                    if ui.button("Clear waypoints").clicked() {
+                        self.waypoints.clear();
+                    }
+                });
+
+                ui.checkbox(&mut self.animate, "Animate search");
+
+                ui.add(
+                    egui::Slider::new(&mut self.frames_per_second, 1..=200)
+                        .text("Animation frames per second")
+                        .integer(),
+                );
+
                 if ui.button("Open grid file…").clicked() {
                     self.upload_file()
                 }
 
+                if ui.button("Open scenario…").clicked() {
+                    self.open_scenario()
+                }
+
+                if ui.button("Save scenario…").clicked() {
+                    self.save_scenario()
+                }
+
+                let status = self.status.lock().expect("Failed to obtain mutex for status").clone();
+                if !status.is_empty() {
+                    ui.colored_label(egui::Color32::RED, status);
+                }
+
                 ui.add_space(WIDGET_SPACING);
 
                 if ui.button("Find Path").clicked() {
-                    self.find_path()
+                    self.find_path(now)
+                }
+
+                if path_len > 0 && self.playback_started.is_none() {
+                    ui.add_space(WIDGET_SPACING);
+                    ui.add(
+                        egui::Slider::new(&mut self.playback_step, 0..=path_len)
+                            .text("Playback step")
+                            .integer(),
+                    );
                 }
             })
         });
         egui::CentralPanel::default().show(ctx, |ui| {
             let panel_size = ui.available_size();
-            let graph_size = graph.lock().expect("Failed to obtain mutex for graph").size();
+            // The timestep of the shown step, which opens and closes the windows and the blockages
+            let timestep = path.path.last().map_or(0, |s| s.step);
+            let (height, width, chargers, depots, windows, blocked) = {
+                let graph = graph.lock().expect("Failed to obtain mutex for graph");
+                let blocked: HashSet<Position> = (0..graph.height()).flat_map(|x| (0..graph.width()).map(move |y| (x, y))).filter(|&u| graph.is_blocked(u, timestep)).collect();
+                (graph.height(), graph.width(), graph.chargers().into_iter().collect::<HashSet<_>>(), graph.depots().into_iter().collect::<HashSet<_>>(), graph.windows().into_iter().collect::<HashMap<_, _>>(), blocked)
+            };
             let rect_size = egui::Vec2::new(
-                (panel_size.x - 20.0) / graph_size as f32,
-                (panel_size.y - 20.0) / graph_size as f32,
+                (panel_size.x - 20.0) / height as f32,
+                (panel_size.y - 20.0) / width as f32,
             );
 
             let (_, painter) = ui.allocate_painter(panel_size, Sense::hover());
@@ -249,8 +617,8 @@ impl eframe::App for MyApp {
             // Use a single loop to accumulate draw calls
             let mut shapes = Vec::new();
 
-            for y in 0..graph_size {
-                for x in 0..graph_size {
+            for y in 0..width {
+                for x in 0..height {
                     let x_coord = x as f32 * rect_size.x + 10.0;
                     let y_coord = y as f32 * rect_size.y + 10.0;
                     let pos = egui::pos2(x_coord, y_coord);
@@ -281,24 +649,26 @@ impl eframe::App for MyApp {
 
             // Only draw text for visible regions (dynamic culling)
             let visible_rect = ui.clip_rect();
-            let path_len = path.lock().expect("Failed to obtain mutex for path").path.len();
 
-            for y in 0..graph_size {
-                for x in 0..graph_size {
+            for y in 0..width {
+                for x in 0..height {
                     let x_coord = x as f32 * rect_size.x + 10.0;
                     let y_coord = y as f32 * rect_size.y + 10.0;
                     let pos = egui::pos2(x_coord, y_coord);
                     let rect = egui::Rect::from_min_size(pos, rect_size);
-                    let path = path.lock().expect("Failed to obtain mutex for path");
                     let steps = path.steps_at((x, y));
 
-                    if(steps.len() > 1) {
+                    if expanded.contains(&(x, y)) {
+                        painter.rect_filled(rect, self.rounding, egui::Color32::from_rgba_premultiplied(120, 170, 255, 60));
+                    }
+
+                    if steps.len() > 1 {
                         painter.rect_filled(rect, self.rounding, egui::Color32::LIGHT_GRAY);
 
                         ui.painter().text(
                             rect.min,
                             egui::Align2::LEFT_TOP,
-                            format!("{}", steps.iter().map(|s| s.step.to_string()).collect::<Vec<String>>().join(" .. ")),
+                            steps.iter().map(|s| s.step.to_string()).collect::<Vec<String>>().join(" .. "),
                             egui::FontId::proportional(8.0), // Reduce font size
                             egui::Color32::DARK_GRAY,
                         );
@@ -306,14 +676,14 @@ impl eframe::App for MyApp {
                         ui.painter().text(
                             rect.max,
                             egui::Align2::RIGHT_BOTTOM,
-                            format!("{}", steps.iter().map(|s| s.score.to_string()).collect::<Vec<String>>().join(" + ")),
+                            steps.iter().map(|s| s.score.to_string()).collect::<Vec<String>>().join(" + "),
                             egui::FontId::proportional(9.0), // Reduce font size
                             egui::Color32::BLACK,
                         );
 
                     } else if let Some(step) = steps.first() {
 
-                        let (r, g, b) = percentage_to_rgb(step.step as f32 / path_len as f32 * 100.0);
+                        let (r, g, b) = percentage_to_rgb(step.step as f32 / elapsed as f32 * 100.0);
 
                         painter.rect_filled(rect, self.rounding, egui::Color32::from_rgba_premultiplied(r, g, b, 100));
 
@@ -338,9 +708,54 @@ impl eframe::App for MyApp {
 
 
 
-                    if visible_rect.intersects(rect) {
-                        let graph = graph.lock().expect("Failed to obtain mutex for graph");
-                        let node = graph.get_score_at((x, y));
+                    if blocked.contains(&(x, y)) {
+                        painter.rect_filled(rect, self.rounding, egui::Color32::from_rgba_unmultiplied(128, 128, 128, 160));
+                    }
+
+                    if chargers.contains(&(x, y)) {
+                        let radius = rect_size.min_elem() / 8.0;
+                        painter.circle_filled(rect.right_top() + egui::vec2(-radius * 1.5, radius * 1.5), radius, egui::Color32::GOLD);
+                    }
+
+                    if let Some(&fraction) = footprint.get(&(x, y)) {
+                        let alpha = (fraction.clamp(0.0, 1.0) * 255.0) as u8;
+                        painter.rect_stroke(rect.shrink(2.0), self.rounding, egui::Stroke::new(2.0, egui::Color32::from_rgba_unmultiplied(0, 0, 255, alpha)), StrokeKind::Inside);
+                    }
+
+                    // The time window, green while it is open at the shown step
+                    if let Some(window) = windows.get(&(x, y)) {
+                        let color = if window.contains(timestep) { egui::Color32::DARK_GREEN } else { egui::Color32::GRAY };
+                        ui.painter().text(
+                            rect.center_top(),
+                            egui::Align2::CENTER_TOP,
+                            format!("{}{window}", if window.required { "!" } else { "" }),
+                            egui::FontId::proportional(8.0),
+                            color,
+                        );
+                    }
+
+                    // The numbers of the waypoints in the cell, in the order they have to be passed
+                    let numbers: Vec<String> = self.waypoints.iter().zip(1..).filter(|&(&u, _)| u == (x, y)).map(|(_, k)| k.to_string()).collect();
+                    if !numbers.is_empty() {
+                        ui.painter().text(
+                            rect.center_bottom(),
+                            egui::Align2::CENTER_BOTTOM,
+                            numbers.join(","),
+                            egui::FontId::proportional(10.0),
+                            egui::Color32::from_rgb(128, 0, 128),
+                        );
+                    }
+
+                    if depots.contains(&(x, y)) {
+                        let side = rect_size.min_elem() / 4.0;
+                        painter.rect_filled(egui::Rect::from_min_size(rect.left_bottom() + egui::vec2(side / 2.0, -side * 1.5), egui::vec2(side, side)), self.rounding, egui::Color32::BROWN);
+                    }
+
+                    if agent == Some((x, y)) {
+                        painter.circle_stroke(rect.center(), rect_size.min_elem() / 3.0, egui::Stroke::new(2.0, egui::Color32::BLUE));
+                    }
+
+                    if let Some(node) = score_grid.get(x).and_then(|row| row.get(y)) && visible_rect.intersects(rect) {
                         ui.painter().text(
                             rect.center(),
                             egui::Align2::CENTER_CENTER,
@@ -352,9 +767,15 @@ impl eframe::App for MyApp {
 
                     }
 
-                    ui.allocate_ui_at_rect(rect, |ui| {
+                    ui.allocate_new_ui(UiBuilder::new().max_rect(rect), |ui| {
                         let (_, res) = ui.allocate_exact_size(rect_size, Sense::click());
-                        if res.clicked() {
+                        // Clicking a waypoint while placing them takes it out again
+                        if res.clicked() && self.placing_waypoints {
+                            match self.waypoints.iter().rposition(|&u| u == (x, y)) {
+                                Some(k) => { self.waypoints.remove(k); }
+                                None => self.waypoints.push((x, y))
+                            }
+                        } else if res.clicked() {
                             self.start = Some((x, y));
                         }
                     });
@@ -0,0 +1,245 @@
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use clap::ValueEnum;
+use pathfinding::{Graph, NoopObserver, PathfindingResult, Planner, PlanningOptions, Position};
+
+/// How the comparison of the planners is written to stdout.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BenchFormat {
+    /// A Markdown table, for pasting into an issue or a pull request
+    #[default]
+    Markdown,
+    /// One row per planner and parameters
+    Csv
+}
+
+/// A grid and a start to plan from, with the options the timesteps and recovery rates are applied to.
+pub struct Instance {
+    pub name: String,
+    pub graph: Arc<Graph>,
+    pub start: Position,
+    pub options: PlanningOptions
+}
+
+/// The outcome of a single planner on a single instance.
+struct Sample {
+    planner: usize,
+    max_timesteps: u32,
+    recovery_rate: u32,
+    score: u64,
+    time: Duration,
+    /// Whether the planner was still running after the timeout, which leaves no score or time.
+    timed_out: bool,
+    /// Whether the path passes [`Graph::verify_path`], only valid paths can win.
+    valid: bool,
+    /// Whether no other planner found a valid path with a higher score on the same instance and parameters.
+    won: bool
+}
+
+/// The statistics of a planner over all instances, for one combination of parameters.
+pub struct Summary {
+    pub planner: Planner,
+    pub max_timesteps: u32,
+    pub recovery_rate: u32,
+    pub runs: usize,
+    /// The number of runs whose path doesn't pass [`Graph::verify_path`], like a path which misses a required time window.
+    pub invalid: usize,
+    /// The number of runs which didn't finish before the timeout, the means only cover the finished
+    /// runs and are NaN when none finished.
+    pub timeouts: usize,
+    pub mean_score: f64,
+    pub score_variance: f64,
+    pub mean_time_ms: f64,
+    pub time_variance_ms: f64,
+    /// The fraction of the instances where the planner found the highest score with a valid path,
+    /// ties count for every tied planner.
+    pub win_rate: f64
+}
+
+/// Returns the mean and the sample variance of the values, both NaN without values.
+fn mean_and_variance(values: &[f64]) -> (f64, f64) {
+    if values.is_empty() {
+        return (f64::NAN, f64::NAN);
+    }
+
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    let variance = if values.len() > 1 {
+        values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0)
+    } else {
+        0.0
+    };
+    (mean, variance)
+}
+
+/// Plans a path in a separate thread, `None` when the planner is still running after the timeout.
+///
+/// The thread of a run which timed out can't be stopped and keeps running until the planner is
+/// done, so the runs after it compete with it for the CPU.
+fn plan_within(instance: &Instance, planner: &Planner, options: &PlanningOptions, timeout: Duration) -> Option<PathfindingResult<u64>> {
+    let (tx, rx) = mpsc::channel();
+    let (graph, planner, options, start) = (Arc::clone(&instance.graph), planner.clone(), options.clone(), instance.start);
+
+    thread::spawn(move || {
+        let _ = tx.send(planner.plan(&graph, start, &options, &mut NoopObserver));
+    });
+
+    rx.recv_timeout(timeout).ok()
+}
+
+/// Runs every planner on every instance for every combination of timesteps and recovery rates,
+/// where `None` keeps the value of the instance, giving up on a run after the timeout.
+pub fn bench(instances: &[Instance], planners: &[Planner], timesteps: Option<&[u32]>, recovery_rates: Option<&[u32]>, timeout: Duration, progress: bool) -> Vec<Summary> {
+    let mut samples = Vec::new();
+
+    for (i, instance) in instances.iter().enumerate() {
+        if progress {
+            eprint!("\rInstance {}/{} - {}", i + 1, instances.len(), instance.name);
+        }
+
+        let timesteps = timesteps.map_or(vec![instance.options.max_timesteps], <[u32]>::to_vec);
+        let recovery_rates = recovery_rates.map_or(vec![instance.options.recovery_rate], <[u32]>::to_vec);
+
+        for &max_timesteps in &timesteps {
+            for &recovery_rate in &recovery_rates {
+                let options = PlanningOptions { max_timesteps, recovery_rate, ..instance.options.clone() };
+
+                let case: Vec<Sample> = planners
+                    .iter()
+                    .enumerate()
+                    .map(|(planner_index, planner)| {
+                        let sample = Sample { planner: planner_index, max_timesteps, recovery_rate, score: 0, time: timeout, timed_out: true, valid: false, won: false };
+                        let started = Instant::now();
+                        let Some(result) = plan_within(instance, planner, &options, timeout) else {
+                            return sample;
+                        };
+                        let time = started.elapsed();
+                        let positions: Vec<Position> = result.path.iter().map(|s| s.node).collect();
+                        let valid = instance.graph.verify_path(&positions, &options).is_ok();
+                        Sample { score: result.score(), time, timed_out: false, valid, ..sample }
+                    })
+                    .collect();
+
+                let best = case.iter().filter(|s| s.valid).map(|s| s.score).max();
+                samples.extend(case.into_iter().map(|s| Sample { won: s.valid && Some(s.score) == best, ..s }));
+            }
+        }
+    }
+
+    if progress {
+        eprintln!();
+    }
+
+    summarize(&samples, planners)
+}
+
+fn summarize(samples: &[Sample], planners: &[Planner]) -> Vec<Summary> {
+    let mut groups: Vec<(usize, u32, u32)> = samples.iter().map(|s| (s.planner, s.max_timesteps, s.recovery_rate)).collect();
+    groups.sort_by_key(|&(planner, max_timesteps, recovery_rate)| (max_timesteps, recovery_rate, planner));
+    groups.dedup();
+
+    groups
+        .into_iter()
+        .map(|(planner, max_timesteps, recovery_rate)| {
+            let group: Vec<&Sample> = samples
+                .iter()
+                .filter(|s| (s.planner, s.max_timesteps, s.recovery_rate) == (planner, max_timesteps, recovery_rate))
+                .collect();
+
+            let finished: Vec<&&Sample> = group.iter().filter(|s| !s.timed_out).collect();
+            let scores: Vec<f64> = finished.iter().map(|s| s.score as f64).collect();
+            let times: Vec<f64> = finished.iter().map(|s| s.time.as_secs_f64() * 1000.0).collect();
+            let (mean_score, score_variance) = mean_and_variance(&scores);
+            let (mean_time_ms, time_variance_ms) = mean_and_variance(&times);
+
+            Summary {
+                planner: planners[planner].clone(),
+                max_timesteps,
+                recovery_rate,
+                runs: group.len(),
+                invalid: finished.iter().filter(|s| !s.valid).count(),
+                timeouts: group.len() - finished.len(),
+                mean_score,
+                score_variance,
+                mean_time_ms,
+                time_variance_ms,
+                win_rate: group.iter().filter(|s| s.won).count() as f64 / group.len() as f64
+            }
+        })
+        .collect()
+}
+
+const COLUMNS: [&str; 11] = ["planner", "max_timesteps", "recovery_rate", "runs", "invalid", "timeouts", "mean_score", "score_variance", "mean_time_ms", "time_variance_ms", "win_rate"];
+
+/// Formats a mean or a variance, `n/a` when every run timed out.
+fn decimal(value: f64, precision: usize) -> String {
+    if value.is_nan() {
+        "n/a".to_string()
+    } else {
+        format!("{value:.precision$}")
+    }
+}
+
+fn row(summary: &Summary) -> [String; 11] {
+    [
+        summary.planner.to_string(),
+        summary.max_timesteps.to_string(),
+        summary.recovery_rate.to_string(),
+        summary.runs.to_string(),
+        summary.invalid.to_string(),
+        summary.timeouts.to_string(),
+        decimal(summary.mean_score, 2),
+        decimal(summary.score_variance, 2),
+        decimal(summary.mean_time_ms, 3),
+        decimal(summary.time_variance_ms, 3),
+        format!("{:.2}", summary.win_rate)
+    ]
+}
+
+pub fn to_csv(summaries: &[Summary]) -> String {
+    let mut csv = COLUMNS.join(",") + "\n";
+    for summary in summaries {
+        csv.push_str(&row(summary).join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+pub fn to_markdown(summaries: &[Summary]) -> String {
+    let mut markdown = format!("| {} |\n", COLUMNS.join(" | "));
+    markdown.push_str(&format!("|{}\n", " --- |".repeat(COLUMNS.len())));
+    for summary in summaries {
+        markdown.push_str(&format!("| {} |\n", row(summary).join(" | ")));
+    }
+    if summaries.iter().any(|summary| summary.timeouts > 0) {
+        markdown.push_str(TIMEOUT_NOTE);
+        markdown.push('\n');
+    }
+    markdown
+}
+
+/// Printed with the comparison when a run timed out, since its planner kept running.
+pub const TIMEOUT_NOTE: &str = "\nThe planners of the runs which timed out kept running in the background, so the times of the runs after them may be too high.";
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn timed_out(planner: usize) -> Sample {
+        Sample { planner, max_timesteps: 10, recovery_rate: 1, score: 0, time: Duration::from_millis(5), timed_out: true, valid: false, won: false }
+    }
+
+    #[test]
+    fn test_summarize_timed_out_runs() {
+        let finished = Sample { score: 7, timed_out: false, valid: true, won: true, ..timed_out(1) };
+        let summaries = summarize(&[timed_out(0), timed_out(0), finished], &[Planner::BestFirstSearch, Planner::DepthFirstSearch]);
+
+        assert_eq!((summaries[0].runs, summaries[0].timeouts, summaries[0].win_rate), (2, 2, 0.0));
+        assert!(summaries[0].mean_score.is_nan() && summaries[0].mean_time_ms.is_nan());
+        assert_eq!(&row(&summaries[0])[6..], ["n/a", "n/a", "n/a", "n/a", "0.00"]);
+        assert_eq!((summaries[1].mean_score, summaries[1].score_variance, summaries[1].win_rate), (7.0, 0.0, 1.0));
+        assert!(to_markdown(&summaries).ends_with(&format!("{TIMEOUT_NOTE}\n")));
+    }
+}
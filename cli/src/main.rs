@@ -1,12 +1,25 @@
-use std::sync::mpsc;
+use std::path::Path;
+use std::sync::{mpsc, Arc};
 use std::thread;
-use std::time::Duration;
+use std::io::{Read, Write};
+use std::time::{Duration, Instant};
 use clap::*;
 use pathfinding::*;
 
+mod bench;
+mod output;
+
+use bench::{BenchFormat, Instance};
+use output::{Format, GridDimensions, Report};
+
+
 #[derive(Parser, Debug)] // requires `derive` feature
 #[command(term_width = 0)] // Just to make testing across clap features easier
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     #[arg(short = 'x')]
     x: Option<usize>,
 
@@ -14,17 +27,642 @@ struct Args {
     y: Option<usize>,
 
     #[arg(short = 'T', required = true)]
-    max_timesteps: u32,
+    max_timesteps: Option<u32>,
 
     #[arg(short = 'R')]
     recovery_rate: Option<u32>,
 
+    /// The cells the agent can move to in a single timestep, `moore` or `von-neumann`
+    #[arg(short = 'N', long, default_value_t = Neighborhood::Moore)]
+    neighborhood: Neighborhood,
+
     #[arg(short, long, value_parser = parse_duration)]
     timeout: Option<Duration>,
 
+    /// Seed for tie-breaking and any randomness in the planner, the same seed gives the same path
+    #[arg(short, long, default_value_t = 0)]
+    seed: u64,
+
+    /// Print a progress line to stderr while the planner is running
+    #[arg(short = 'P', long)]
+    progress: bool,
+
+    /// The planner used to find the path
+    #[arg(long, default_value_t = Planner::BestFirstSearch)]
+    planner: Planner,
+
+    /// How cells which cost points are treated, `accept`, `avoid` or `look-ahead`
+    #[arg(long, default_value_t = Penalties::Accept)]
+    penalties: Penalties,
+
+    /// The type of the values of the grid
+    #[arg(long, value_enum, default_value_t = Values::Unsigned)]
+    values: Values,
+
+    /// How the path is written to stdout
+    #[arg(short, long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+
     /// Allow invalid UTF-8 paths
     #[arg(short = 'I', value_name = "FILE", value_hint = clap::ValueHint::DirPath, required = true)]
-    file: std::path::PathBuf
+    file: Option<std::path::PathBuf>,
+
+    #[command(flatten)]
+    image: ImageArgs,
+
+    #[command(flatten)]
+    semantics: SemanticsArgs,
+
+    #[command(flatten)]
+    costs: CostArgs,
+
+    #[command(flatten)]
+    energy: EnergyArgs,
+
+    #[command(flatten)]
+    footprint: FootprintArgs,
+
+    #[command(flatten)]
+    harvest: HarvestArgs,
+
+    #[command(flatten)]
+    hopper: HopperArgs,
+
+    #[command(flatten)]
+    windows: WindowArgs,
+
+    #[command(flatten)]
+    waypoints: WaypointArgs,
+
+    #[command(flatten)]
+    revisits: RevisitArgs,
+
+    #[command(flatten)]
+    blockages: BlockageArgs
+}
+
+/// How a PGM or PNG image given as the grid file is turned into scores.
+#[derive(clap::Args, Debug)]
+struct ImageArgs {
+    /// The value of a pixel which becomes its score, `luminance`, `red`, `green`, `blue` or `alpha`
+    #[arg(long, default_value_t = Channel::Luminance)]
+    image_channel: Channel,
+
+    /// Multiplies the value of a pixel to get its score
+    #[arg(long, default_value_t = 1.0)]
+    image_scale: f64,
+
+    /// Added to the scaled value of a pixel to get its score
+    #[arg(long, default_value_t = 0.0, allow_negative_numbers = true)]
+    image_offset: f64,
+
+    /// Downsamples the image to a grid of `<height>x<width>` cells, averaging the pixels of a cell
+    #[arg(long, value_parser = parse_size)]
+    image_size: Option<(usize, usize)>
+}
+
+impl ImageArgs {
+    fn options(&self) -> ImageOptions {
+        ImageOptions { channel: self.image_channel, scale: self.image_scale, offset: self.image_offset, size: self.image_size }
+    }
+}
+
+#[derive(clap::Args, Debug)]
+struct SemanticsArgs {
+    /// Whether -T counts the `cells` of a path including the start, or the `moves` after the start
+    #[arg(long, default_value_t = Horizon::Cells)]
+    horizon: Horizon,
+
+    /// Whether the score of the start is `collected` on the first step or `skipped`
+    #[arg(long, default_value_t = StartCell::Collected)]
+    start_cell: StartCell,
+
+    /// Whether the cells recover `after-collection` or `before-collection` within a step
+    #[arg(long, default_value_t = RecoveryOrder::AfterCollection)]
+    recovery_order: RecoveryOrder
+}
+
+impl SemanticsArgs {
+    fn semantics(&self) -> TimestepSemantics {
+        TimestepSemantics { horizon: self.horizon, start: self.start_cell, recovery: self.recovery_order }
+    }
+}
+
+/// How long moves take.
+#[derive(clap::Args, Debug)]
+struct CostArgs {
+    /// A grid file with the number of timesteps it takes to enter each cell, at least 1
+    #[arg(long, value_name = "FILE", value_hint = clap::ValueHint::FilePath)]
+    entry_costs: Option<std::path::PathBuf>,
+
+    /// The timesteps of a horizontal or vertical move, multiplied by the entry cost of the cell
+    #[arg(long, default_value_t = 1)]
+    straight_cost: u32,
+
+    /// The timesteps of a diagonal move, multiplied by the entry cost of the cell
+    #[arg(long, default_value_t = 1)]
+    diagonal_cost: u32
+}
+
+impl CostArgs {
+    fn move_costs(&self) -> MoveCosts {
+        MoveCosts { straight: self.straight_cost, diagonal: self.diagonal_cost }
+    }
+
+    /// Adds the entry cost layer to the graph, exiting with a message when it can't be loaded.
+    fn apply<S: Score>(&self, graph: Graph<S>, image: &ImageArgs) -> Graph<S> {
+        let Some(path) = &self.entry_costs else {
+            return graph;
+        };
+
+        let (costs, _) = load_grid(path, image);
+        graph.with_entry_costs(&costs).unwrap_or_else(|error| {
+            eprintln!("Unable to load entry costs: {error}");
+            std::process::exit(2);
+        })
+    }
+}
+
+/// The cells the agent collects when it enters a cell.
+#[derive(clap::Args, Debug)]
+struct FootprintArgs {
+    /// The shape of the collected area around the agent, `cell`, `square` or `disk`
+    #[arg(long, default_value_t = Footprint::Cell)]
+    footprint: Footprint,
+
+    /// The radius of the footprint in cells, 1 by default, so a `square` is 3x3
+    #[arg(long, requires = "footprint")]
+    footprint_radius: Option<f64>,
+
+    /// Collect a fraction of the cells of the footprint which shrinks with their distance to the agent
+    #[arg(long, requires = "footprint")]
+    footprint_weighted: bool
+}
+
+impl FootprintArgs {
+    fn footprint(&self) -> Footprint {
+        let weighted = self.footprint_weighted;
+        match self.footprint {
+            Footprint::Cell => Footprint::Cell,
+            Footprint::Square { radius, .. } => Footprint::Square { radius: self.footprint_radius.map_or(radius, |r| r.max(0.0).round() as u32), weighted },
+            Footprint::Disk { radius, .. } => Footprint::Disk { radius: self.footprint_radius.unwrap_or(radius), weighted }
+        }
+    }
+}
+
+/// How much of the cells the agent takes when it collects them.
+#[derive(clap::Args, Debug)]
+struct HarvestArgs {
+    /// Take the whole score, a `fraction` of it, a `limit` or an even share over several `steps`
+    /// on the cell, waiting on it
+    #[arg(long, default_value_t = Harvest::Full)]
+    harvest: Harvest,
+
+    /// The fraction, the limit or the number of steps, 0.5, 1 and 2 by default
+    #[arg(long, requires = "harvest")]
+    harvest_amount: Option<f64>
+}
+
+impl HarvestArgs {
+    fn harvest(&self) -> Harvest {
+        let Some(amount) = self.harvest_amount else {
+            return self.harvest;
+        };
+        match self.harvest {
+            Harvest::Full => Harvest::Full,
+            Harvest::Fraction { .. } => Harvest::Fraction { fraction: amount },
+            Harvest::Limit { .. } => Harvest::Limit { amount: amount.max(0.0).round() as u32 },
+            Harvest::Steps { .. } => Harvest::Steps { steps: amount.max(1.0).round() as u32 }
+        }
+    }
+}
+
+/// The battery of the agent.
+#[derive(clap::Args, Debug)]
+struct EnergyArgs {
+    /// The energy of a full battery, moves don't use energy when left out
+    #[arg(long, value_name = "CAPACITY")]
+    energy: Option<u32>,
+
+    /// The energy a move uses for every timestep it takes
+    #[arg(long, default_value_t = 1, requires = "energy")]
+    energy_per_timestep: u32,
+
+    /// A file with the charging stations which refill the battery, one position per line written as `x y` or `x,y`
+    #[arg(long, value_name = "FILE", value_hint = clap::ValueHint::FilePath, requires = "energy")]
+    chargers: Option<std::path::PathBuf>
+}
+
+impl EnergyArgs {
+    fn budget(&self) -> Option<EnergyBudget> {
+        self.energy.map(|capacity| EnergyBudget { capacity, per_timestep: self.energy_per_timestep })
+    }
+
+    /// Adds the charging stations to the graph, exiting with a message when they can't be loaded.
+    fn apply<S: Score>(&self, mut graph: Graph<S>) -> Graph<S> {
+        let Some(path) = &self.chargers else {
+            return graph;
+        };
+
+        for charger in read_positions(path, "chargers", &graph) {
+            graph.add_charger(charger);
+        }
+        graph
+    }
+}
+
+/// The load the agent can carry.
+#[derive(clap::Args, Debug)]
+struct HopperArgs {
+    /// The most score the agent can carry to a depot, the collected score counts right away when left out
+    #[arg(long, value_name = "CAPACITY")]
+    hopper: Option<u32>,
+
+    /// What happens to the score which doesn't fit, `lost` or `blocked` to leave the cell alone
+    #[arg(long, default_value_t = Overflow::Lost, requires = "hopper")]
+    hopper_overflow: Overflow,
+
+    /// A file with the depots which empty the hopper, one position per line written as `x y` or `x,y`
+    #[arg(long, value_name = "FILE", value_hint = clap::ValueHint::FilePath, requires = "hopper")]
+    depots: Option<std::path::PathBuf>
+}
+
+impl HopperArgs {
+    fn hopper(&self) -> Option<Hopper> {
+        self.hopper.map(|capacity| Hopper { capacity, overflow: self.hopper_overflow })
+    }
+
+    /// Adds the depots to the graph, exiting with a message when they can't be loaded.
+    fn apply<S: Score>(&self, mut graph: Graph<S>) -> Graph<S> {
+        let Some(path) = &self.depots else {
+            return graph;
+        };
+
+        for depot in read_positions(path, "depots", &graph) {
+            graph.add_depot(depot);
+        }
+        graph
+    }
+}
+
+/// The time windows of the cells.
+#[derive(clap::Args, Debug)]
+struct WindowArgs {
+    /// A file with the time windows of the cells which are only worth collecting for a while, one
+    /// per line written as `x y open close`, followed by `required` when the path has to meet it
+    #[arg(long, value_name = "FILE", value_hint = clap::ValueHint::FilePath)]
+    windows: Option<std::path::PathBuf>
+}
+
+impl WindowArgs {
+    /// Adds the time windows to the graph, exiting with a message when they can't be loaded.
+    fn apply<S: Score>(&self, mut graph: Graph<S>) -> Graph<S> {
+        let Some(path) = &self.windows else {
+            return graph;
+        };
+
+        let exit = |error: &dyn std::fmt::Display| -> ! {
+            eprintln!("Unable to load windows: {error}");
+            std::process::exit(2);
+        };
+
+        let contents = std::fs::read_to_string(path).unwrap_or_else(|error| exit(&format!("{}: {error}", path.display())));
+        for (node, window) in parse_windows(&contents).unwrap_or_else(|error| exit(&error)) {
+            if !graph.contains(node) {
+                exit(&format!("{node:?} is outside of the grid"));
+            }
+            graph.add_window(node, window);
+        }
+        graph
+    }
+}
+
+/// The cells which are closed for a while.
+#[derive(clap::Args, Debug)]
+struct BlockageArgs {
+    /// A file with the cells which are closed for a while, one per line written as `x y from until`,
+    /// or `x y x y from until` for the rectangle between two corners
+    #[arg(long, value_name = "FILE", value_hint = clap::ValueHint::FilePath)]
+    blockages: Option<std::path::PathBuf>
+}
+
+impl BlockageArgs {
+    /// Adds the blockages to the graph, exiting with a message when they can't be loaded.
+    fn apply<S: Score>(&self, mut graph: Graph<S>) -> Graph<S> {
+        let Some(path) = &self.blockages else {
+            return graph;
+        };
+
+        let exit = |error: &dyn std::fmt::Display| -> ! {
+            eprintln!("Unable to load blockages: {error}");
+            std::process::exit(2);
+        };
+
+        let contents = std::fs::read_to_string(path).unwrap_or_else(|error| exit(&format!("{}: {error}", path.display())));
+        for blockage in parse_blockages(&contents).unwrap_or_else(|error| exit(&error)) {
+            if let Some(u) = [Some(blockage.node), blockage.corner].into_iter().flatten().find(|&u| !graph.contains(u)) {
+                exit(&format!("{u:?} is outside of the grid"));
+            }
+            graph.add_blockage(blockage);
+        }
+        graph
+    }
+}
+
+/// The cells a path has to pass through.
+#[derive(clap::Args, Debug)]
+struct WaypointArgs {
+    /// A file with the waypoints the path has to pass through in order, one position per line
+    /// written as `x y` or `x,y`
+    #[arg(long, value_name = "FILE", value_hint = clap::ValueHint::FilePath)]
+    waypoints: Option<std::path::PathBuf>
+}
+
+impl WaypointArgs {
+    /// Loads the waypoints, exiting with a message when they can't be loaded.
+    fn waypoints<S: Score>(&self, graph: &Graph<S>) -> Vec<Position> {
+        self.waypoints.as_deref().map_or_else(Vec::new, |path| read_positions(path, "waypoints", graph))
+    }
+}
+
+/// How often and how soon the agent may enter a cell again.
+#[derive(clap::Args, Debug)]
+struct RevisitArgs {
+    /// The most times the agent may enter a cell again, without a limit when left out
+    #[arg(long, value_name = "COUNT")]
+    max_revisits: Option<u32>,
+
+    /// The fewest timesteps the agent has to spend elsewhere before it enters a cell again
+    #[arg(long, default_value_t = 0)]
+    cooldown: u32
+}
+
+impl RevisitArgs {
+    fn revisits(&self) -> Revisits {
+        Revisits { limit: self.max_revisits, cooldown: self.cooldown }
+    }
+}
+
+/// Reads a file of positions inside the graph, exiting with a message naming what they are when they can't be loaded.
+fn read_positions<S: Score>(path: &std::path::Path, what: &str, graph: &Graph<S>) -> Vec<Position> {
+    let exit = |error: &dyn std::fmt::Display| -> ! {
+        eprintln!("Unable to load {what}: {error}");
+        std::process::exit(2);
+    };
+
+    let contents = std::fs::read_to_string(path).unwrap_or_else(|error| exit(&format!("{}: {error}", path.display())));
+    let positions = parse_positions(&contents).unwrap_or_else(|error| exit(&error));
+    if let Some(u) = positions.iter().find(|&&u| !graph.contains(u)) {
+        exit(&format!("{u:?} is outside of the grid"));
+    }
+    positions
+}
+
+/// The type of the values of a grid.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum Values {
+    /// Non-negative integers
+    #[default]
+    Unsigned,
+    /// Integers, where negative cells are penalties
+    Signed,
+    /// Decimals, where negative cells are penalties
+    Float
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Checks that a path follows the rules of the planners and prints the score of every step
+    Verify(Box<VerifyArgs>),
+    /// Plans a path from every start of a TOML or JSON scenario file
+    Run(RunArgs),
+    /// Converts a grid file or image to the binary grid format, which opens almost instantly
+    Convert(ConvertArgs),
+    /// Generates a reproducible grid from a seeded distribution
+    Generate(GenerateArgs),
+    /// Compares planners over a directory of scenarios or generated grids
+    Bench(BenchArgs)
+}
+
+#[derive(clap::Args, Debug)]
+struct BenchArgs {
+    /// The planners to compare, separated by commas
+    #[arg(long, value_delimiter = ',', default_value = "best-first")]
+    planners: Vec<Planner>,
+
+    /// A directory of TOML or JSON scenarios, every start of every scenario is an instance
+    #[arg(long, value_name = "DIR", value_hint = clap::ValueHint::DirPath, required_unless_present = "generate", conflicts_with = "generate")]
+    scenarios: Option<std::path::PathBuf>,
+
+    /// Generates an instance for every seed instead, starting in the center of the grid
+    #[arg(long, value_name = "DISTRIBUTION")]
+    generate: Option<Distribution>,
+
+    /// The number of generated instances, which use the seeds 0 up to this number
+    #[arg(long, default_value_t = 10)]
+    seeds: u64,
+
+    /// The size of the generated grids as `<height>x<width>`
+    #[arg(long, value_parser = parse_size, default_value = "20x20")]
+    size: (usize, usize),
+
+    /// The highest score of a cell of the generated grids, the lowest is 0
+    #[arg(long, default_value_t = 9)]
+    max: u32,
+
+    /// The maximum numbers of timesteps to compare, separated by commas. Defaults to the value of
+    /// each scenario, or 20 for generated grids
+    #[arg(short = 'T', value_delimiter = ',')]
+    timesteps: Vec<u32>,
+
+    /// The recovery rates to compare, separated by commas. Defaults to the value of each
+    /// scenario, or 1 for generated grids
+    #[arg(short = 'R', value_delimiter = ',')]
+    recovery_rates: Vec<u32>,
+
+    /// Gives up on a run of a planner after this many milliseconds, which is reported as a
+    /// timeout. Defaults to 2000
+    #[arg(short, long, value_parser = parse_duration)]
+    timeout: Option<Duration>,
+
+    /// Print a progress line to stderr while the planners are running
+    #[arg(short = 'P', long)]
+    progress: bool,
+
+    /// How the comparison is written to stdout
+    #[arg(short, long, value_enum, default_value_t = BenchFormat::Markdown)]
+    format: BenchFormat
+}
+
+#[derive(clap::Args, Debug)]
+struct GenerateArgs {
+    /// How the scores are spread, `uniform`, `hotspots`, `noise`, `gradient`, `checkerboard` or `treasure`
+    #[arg(value_name = "DISTRIBUTION")]
+    distribution: Distribution,
+
+    /// The size of the grid as `<height>x<width>`
+    #[arg(long, value_parser = parse_size, default_value = "20x20")]
+    size: (usize, usize),
+
+    /// The lowest score of a cell
+    #[arg(long, default_value_t = 0)]
+    min: u32,
+
+    /// The highest score of a cell
+    #[arg(long, default_value_t = 9)]
+    max: u32,
+
+    /// The same seed and parameters always give the same grid
+    #[arg(short, long, default_value_t = 0)]
+    seed: u64,
+
+    /// The number of hotspots
+    #[arg(long)]
+    count: Option<usize>,
+
+    /// The spread of a hotspot in cells
+    #[arg(long)]
+    radius: Option<f64>,
+
+    /// The distance in cells between the features of the noise
+    #[arg(long)]
+    scale: Option<f64>,
+
+    /// The size in cells of a checkerboard square
+    #[arg(long)]
+    tile: Option<usize>,
+
+    /// The fraction of cells with a treasure
+    #[arg(long)]
+    density: Option<f64>,
+
+    /// Writes the grid to this file instead of stdout, in the binary format with `--cell-type`
+    #[arg(short, long, value_name = "OUTPUT", value_hint = clap::ValueHint::FilePath)]
+    output: Option<std::path::PathBuf>,
+
+    /// Writes a binary grid with cells of this type, `u8`, `u16` or `u32`
+    #[arg(long, requires = "output")]
+    cell_type: Option<CellType>
+}
+
+impl GenerateArgs {
+    fn generator(&self) -> GridGenerator {
+        let distribution = match self.distribution {
+            Distribution::Hotspots { count, radius } => Distribution::Hotspots { count: self.count.unwrap_or(count), radius: self.radius.unwrap_or(radius) },
+            Distribution::Noise { scale } => Distribution::Noise { scale: self.scale.unwrap_or(scale) },
+            Distribution::Checkerboard { tile } => Distribution::Checkerboard { tile: self.tile.unwrap_or(tile) },
+            Distribution::Treasure { density } => Distribution::Treasure { density: self.density.unwrap_or(density) },
+            ref distribution => distribution.clone()
+        };
+
+        GridGenerator { min: self.min, max: self.max, seed: self.seed, ..GridGenerator::new(self.size.0, self.size.1, distribution) }
+    }
+}
+
+#[derive(clap::Args, Debug)]
+struct ConvertArgs {
+    /// The grid file to convert
+    #[arg(short = 'I', value_name = "FILE", value_hint = clap::ValueHint::FilePath)]
+    file: std::path::PathBuf,
+
+    #[command(flatten)]
+    image: ImageArgs,
+
+    /// The binary grid file to write
+    #[arg(short, long, value_name = "OUTPUT", value_hint = clap::ValueHint::FilePath)]
+    output: std::path::PathBuf,
+
+    /// How the cells are stored, `u8`, `u16` or `u32`. Only `u32` grids are used without copying
+    /// when opened, the smallest type which fits every score is used when left out
+    #[arg(long)]
+    cell_type: Option<CellType>
+}
+
+#[derive(clap::Args, Debug)]
+struct RunArgs {
+    /// The scenario file, JSON when the extension is `.json` and TOML otherwise
+    #[arg(value_name = "SCENARIO", value_hint = clap::ValueHint::FilePath)]
+    scenario: std::path::PathBuf,
+
+    #[arg(short, long, value_parser = parse_duration)]
+    timeout: Option<Duration>,
+
+    /// Print a progress line to stderr while the planner is running
+    #[arg(short = 'P', long)]
+    progress: bool,
+
+    /// How the paths are written to stdout
+    #[arg(short, long, value_enum, default_value_t = Format::Text)]
+    format: Format
+}
+
+#[derive(clap::Args, Debug)]
+struct VerifyArgs {
+    /// The maximum number of timesteps, defaults to the time the path takes
+    #[arg(short = 'T')]
+    max_timesteps: Option<u32>,
+
+    #[arg(short = 'R')]
+    recovery_rate: Option<u32>,
+
+    /// The cells the agent can move to in a single timestep, `moore` or `von-neumann`
+    #[arg(short = 'N', long, default_value_t = Neighborhood::Moore)]
+    neighborhood: Neighborhood,
+
+    /// The grid file
+    #[arg(short = 'I', value_name = "FILE", value_hint = clap::ValueHint::FilePath)]
+    file: std::path::PathBuf,
+
+    /// The path to verify, one position per line written as `x y` or `x,y`
+    #[arg(short = 'p', long = "path", value_name = "PATH_FILE", value_hint = clap::ValueHint::FilePath)]
+    path: std::path::PathBuf,
+
+    /// How the scored path is written to stdout
+    #[arg(short, long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+
+    /// The type of the values of the grid
+    #[arg(long, value_enum, default_value_t = Values::Unsigned)]
+    values: Values,
+
+    #[command(flatten)]
+    image: ImageArgs,
+
+    #[command(flatten)]
+    semantics: SemanticsArgs,
+
+    #[command(flatten)]
+    costs: CostArgs,
+
+    #[command(flatten)]
+    energy: EnergyArgs,
+
+    #[command(flatten)]
+    footprint: FootprintArgs,
+
+    #[command(flatten)]
+    harvest: HarvestArgs,
+
+    #[command(flatten)]
+    hopper: HopperArgs,
+
+    #[command(flatten)]
+    windows: WindowArgs,
+
+    #[command(flatten)]
+    waypoints: WaypointArgs,
+
+    #[command(flatten)]
+    revisits: RevisitArgs,
+
+    #[command(flatten)]
+    blockages: BlockageArgs
+}
+
+fn parse_size(s: &str) -> Result<(usize, usize), &'static str> {
+    s.split_once(['x', 'X'])
+        .and_then(|(height, width)| Some((height.trim().parse().ok()?, width.trim().parse().ok()?)))
+        .ok_or("Invalid size, expected <height>x<width>")
 }
 
 fn parse_duration(s: &str) -> Result<Duration, &'static str> {
@@ -35,35 +673,543 @@ fn parse_duration(s: &str) -> Result<Duration, &'static str> {
 
 
 
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Observer which prints the progress of a planner as a single line on stderr.
+struct ProgressLine {
+    /// The number of steps of a complete path.
+    max_timesteps: u32,
+    last_print: Option<Instant>
+}
+
+impl<T: std::fmt::Display> PathfindingObserver<T> for ProgressLine {
+    fn on_event(&mut self, event: PathfindingEvent<'_, T>) {
+        if let PathfindingEvent::IterationFinished { iteration, score } = event {
+            let due = self.last_print.is_none_or(|t| t.elapsed() >= PROGRESS_INTERVAL);
+
+            if due || iteration == self.max_timesteps {
+                eprint!("\rStep {iteration}/{} - score {score}", self.max_timesteps);
+                let _ = std::io::stderr().flush();
+                self.last_print = Some(Instant::now());
+            }
+        }
+    }
+}
+
+/// Parses a path file with one position per line, written as `x y` or `x,y`.
+fn parse_positions(contents: &str) -> Result<Vec<Position>, String> {
+    contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            let coordinates: Vec<&str> = line.split(|c: char| c == ',' || c.is_whitespace()).filter(|c| !c.is_empty()).collect();
+            match coordinates.as_slice() {
+                [x, y] => match (x.parse(), y.parse()) {
+                    (Ok(x), Ok(y)) => Ok((x, y)),
+                    _ => Err(format!("line {}: invalid position '{line}'", i + 1))
+                },
+                _ => Err(format!("line {}: expected two coordinates, got '{line}'", i + 1))
+            }
+        })
+        .collect()
+}
+
+/// Parses time windows, one per line as `x y open close`, optionally followed by `required`, with
+/// the values separated by whitespace or commas.
+fn parse_windows(contents: &str) -> Result<Vec<(Position, TimeWindow)>, String> {
+    contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            let values: Vec<&str> = line.split(|c: char| c == ',' || c.is_whitespace()).filter(|c| !c.is_empty()).collect();
+            let (values, required) = match values.as_slice() {
+                [values @ .., "required"] => (values, true),
+                values => (values, false)
+            };
+            let [x, y, open, close] = values else {
+                return Err(format!("line {}: expected a position, an opening and a closing timestep, got '{line}'", i + 1));
+            };
+
+            match (x.parse(), y.parse(), open.parse(), close.parse()) {
+                (Ok(x), Ok(y), Ok(open), Ok(close)) if open <= close => Ok(((x, y), TimeWindow { open, close, required })),
+                _ => Err(format!("line {}: invalid time window '{line}'", i + 1))
+            }
+        })
+        .collect()
+}
+
+/// Parses blockages, one per line as `x y from until` for a cell or `x y x y from until` for the
+/// rectangle between two corners, with the values separated by whitespace or commas.
+fn parse_blockages(contents: &str) -> Result<Vec<Blockage>, String> {
+    contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            let values: Vec<&str> = line.split(|c: char| c == ',' || c.is_whitespace()).filter(|c| !c.is_empty()).collect();
+            let (cells, from, until) = match values.as_slice() {
+                [cells @ .., from, until] if cells.len() == 2 || cells.len() == 4 => (cells, from, until),
+                _ => return Err(format!("line {}: expected a position or two corners, a first and a last timestep, got '{line}'", i + 1))
+            };
+
+            let cells: Result<Vec<usize>, _> = cells.iter().map(|c| c.parse()).collect();
+            match (cells, from.parse(), until.parse()) {
+                (Ok(cells), Ok(from), Ok(until)) if from <= until => Ok(match cells[..] {
+                    [x, y, cx, cy] => Blockage::region((x, y), (cx, cy), from, until),
+                    _ => Blockage::cell((cells[0], cells[1]), from, until)
+                }),
+                _ => Err(format!("line {}: invalid blockage '{line}'", i + 1))
+            }
+        })
+        .collect()
+}
+
+/// Loads a grid file in the text or binary format, an ESRI ASCII grid or an image, exiting with a
+/// message pointing at the problem when it can't be loaded. The georeference of an ESRI ASCII grid
+/// is kept, so paths can be written as world coordinates.
+fn load_grid(path: &Path, image: &ImageArgs) -> (Graph, Option<Georeference>) {
+    let exit = |error: &dyn std::fmt::Display| -> ! {
+        eprintln!("Unable to load grid: {error}");
+        std::process::exit(2);
+    };
+
+    let mut head = Vec::new();
+    if let Err(error) = std::fs::File::open(path).and_then(|file| file.take(64).read_to_end(&mut head)) {
+        exit(&format!("{}: {error}", path.display()));
+    }
+
+    if Graph::is_esri_ascii(&head) {
+        let contents = std::fs::read_to_string(path).unwrap_or_else(|error| exit(&format!("{}: {error}", path.display())));
+        return match Graph::from_esri_ascii(&contents) {
+            Ok((graph, georeference)) => (graph, Some(georeference)),
+            Err(error) => exit(&error)
+        };
+    }
+
+    match Graph::open_with(path, &image.options()) {
+        Ok(graph) => (graph, None),
+        Err(error) => exit(&error)
+    }
+}
+
+/// Loads a grid like [`load_grid`] with values of another type. Text grids and ESRI ASCII grids
+/// are parsed as that type, so they can hold negative and fractional values, while the values of
+/// binary grids and images are converted.
+fn load_values<S: Score>(path: &Path, image: &ImageArgs) -> (Graph<S>, Option<Georeference>) {
+    let contents = match std::fs::read(path) {
+        Ok(contents) => contents,
+        Err(error) => {
+            eprintln!("Unable to load grid: {}: {error}", path.display());
+            std::process::exit(2);
+        }
+    };
+
+    if Graph::is_binary_grid(&contents) || Graph::is_image(&contents) {
+        let (graph, _) = load_grid(path, image);
+        let rows: Vec<Vec<S>> = graph.to_rows().iter().map(|row| row.iter().map(|&v| S::from_f64(v as f64)).collect()).collect();
+        return (Graph::from_values(&rows), None);
+    }
+
+    let parsed = match std::str::from_utf8(&contents) {
+        Ok(contents) if Graph::is_esri_ascii(contents.as_bytes()) => Graph::from_esri_ascii_values(contents).map(|(graph, georeference)| (graph, Some(georeference))),
+        Ok(contents) => Graph::parse_values(contents).map(|graph| (graph, None)),
+        Err(_) => Err(GridParseError::InvalidUtf8)
+    };
+
+    parsed.unwrap_or_else(|error| {
+        eprintln!("Unable to load grid: {error}");
+        std::process::exit(2);
+    })
+}
+
+fn convert(args: ConvertArgs) {
+    let (graph, _) = load_grid(&args.file, &args.image);
+    let cell_type = args.cell_type.unwrap_or_else(|| {
+        let max = (0..graph.height())
+            .flat_map(|i| (0..graph.width()).map(move |j| (i, j)))
+            .map(|u| *graph.get_score_at(u))
+            .max()
+            .unwrap_or(0);
+        CellType::smallest_for(max)
+    });
+
+    if let Err(error) = graph.write_binary(&args.output, cell_type) {
+        eprintln!("Unable to convert grid: {error}");
+        std::process::exit(1);
+    }
+
+    println!("Wrote a {}x{} grid of {cell_type} cells to {}", graph.height(), graph.width(), args.output.display());
+}
+
+fn generate(args: GenerateArgs) {
+    let graph = args.generator().generate();
+
+    let result = match (&args.output, args.cell_type) {
+        (Some(output), Some(cell_type)) => graph.write_binary(output, cell_type).map_err(|e| e.to_string()),
+        (Some(output), None) => std::fs::write(output, graph.to_text()).map_err(|e| format!("{}: {e}", output.display())),
+        (None, _) => {
+            print!("{}", graph.to_text());
+            Ok(())
+        }
+    };
+
+    if let Err(error) = result {
+        eprintln!("Unable to write grid: {error}");
+        std::process::exit(1);
+    }
+}
+
+/// Loads every start of every scenario in the directory as an instance.
+fn scenario_instances(dir: &Path) -> Vec<Instance> {
+    let mut paths: Vec<_> = match std::fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.extension().is_some_and(|e| e == "toml" || e == "json"))
+            .collect(),
+        Err(error) => {
+            eprintln!("Unable to read scenarios: {}: {error}", dir.display());
+            std::process::exit(2);
+        }
+    };
+    paths.sort();
+
+    let mut instances = Vec::new();
+    for path in paths {
+        let graph = Scenario::from_file(&path).and_then(|scenario| Ok((Arc::new(scenario.graph(dir)?), scenario)));
+        let (graph, scenario) = match graph {
+            Ok(loaded) => loaded,
+            Err(error) => {
+                eprintln!("Unable to load scenario {}: {error}", path.display());
+                std::process::exit(2);
+            }
+        };
+
+        let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+        for &start in &scenario.starts {
+            instances.push(Instance { name: name.clone(), graph: Arc::clone(&graph), start, options: scenario.options.clone() });
+        }
+    }
+
+    instances
+}
+
+fn bench(args: BenchArgs) {
+    let instances = match (&args.scenarios, &args.generate) {
+        (Some(dir), _) => scenario_instances(dir),
+        (None, Some(distribution)) => (0..args.seeds)
+            .map(|seed| {
+                let (height, width) = args.size;
+                let generator = GridGenerator { max: args.max, seed, ..GridGenerator::new(height, width, distribution.clone()) };
+                Instance {
+                    name: format!("{distribution} seed {seed}"),
+                    graph: Arc::new(generator.generate()),
+                    start: (height / 2, width / 2),
+                    options: PlanningOptions { seed, ..PlanningOptions::new(20, 1) }
+                }
+            })
+            .collect(),
+        (None, None) => unreachable!("clap requires --scenarios or --generate")
+    };
+
+    if instances.is_empty() {
+        eprintln!("No instances to benchmark");
+        std::process::exit(2);
+    }
+
+    let timesteps = (!args.timesteps.is_empty()).then_some(args.timesteps.as_slice());
+    let recovery_rates = (!args.recovery_rates.is_empty()).then_some(args.recovery_rates.as_slice());
+    let timeout = args.timeout.unwrap_or(Duration::from_secs(2));
+    let summaries = bench::bench(&instances, &args.planners, timesteps, recovery_rates, timeout, args.progress);
+
+    match args.format {
+        BenchFormat::Markdown => print!("{}", bench::to_markdown(&summaries)),
+        BenchFormat::Csv => {
+            print!("{}", bench::to_csv(&summaries));
+            if summaries.iter().any(|summary| summary.timeouts > 0) {
+                eprintln!("{}", bench::TIMEOUT_NOTE.trim_start());
+            }
+        }
+    }
+}
+
+fn verify(args: VerifyArgs) {
+    match args.values {
+        Values::Unsigned => verify_values(&args, load_grid(&args.file, &args.image)),
+        Values::Signed => verify_values::<i64>(&args, load_values(&args.file, &args.image)),
+        Values::Float => verify_values::<f64>(&args, load_values(&args.file, &args.image))
+    }
+}
+
+fn verify_values<S: Score>(args: &VerifyArgs, (graph, georeference): (Graph<S>, Option<Georeference>))
+where
+    S::Total: serde::Serialize
+{
+    let contents = match std::fs::read_to_string(&args.path) {
+        Ok(contents) => contents,
+        Err(error) => {
+            eprintln!("Unable to load path file: {}: {error}", args.path.display());
+            std::process::exit(2);
+        }
+    };
+
+    let positions = match parse_positions(&contents) {
+        Ok(positions) => positions,
+        Err(error) => {
+            eprintln!("Invalid path file: {error}");
+            std::process::exit(2);
+        }
+    };
+
+    let graph = args.blockages.apply(args.windows.apply(args.hopper.apply(args.energy.apply(args.costs.apply(graph, &args.image)))));
+    let mut options = PlanningOptions {
+        neighborhood: args.neighborhood,
+        semantics: args.semantics.semantics(),
+        move_costs: args.costs.move_costs(),
+        footprint: args.footprint.footprint(),
+        harvest: args.harvest.harvest(),
+        energy: args.energy.budget(),
+        hopper: args.hopper.hopper(),
+        waypoints: args.waypoints.waypoints(&graph),
+        revisits: args.revisits.revisits(),
+        ..PlanningOptions::new(args.max_timesteps.unwrap_or(u32::MAX), args.recovery_rate.unwrap_or(1))
+    };
+
+    match graph.verify_path(&positions, &options) {
+        Ok(result) => {
+            // Only the windows which are not required, a path which misses a required one is invalid
+            let missed = graph.missed_windows(&positions, &options);
+
+            if args.max_timesteps.is_none() {
+                // Without a maximum, the path takes exactly as long as it may
+                let elapsed = result.path.last().map_or(0, |s| s.step);
+                options.max_timesteps = match options.semantics.horizon {
+                    Horizon::Cells => elapsed,
+                    Horizon::Moves => elapsed.saturating_sub(1)
+                };
+            }
+
+            match args.format {
+                Format::Text => {
+                    for step in &result.path {
+                        let mut line = format!("Step {}: {:?} scores {}", step.step, step.node, step.score);
+                        if let Some(energy) = step.energy {
+                            line.push_str(&format!(", energy left {energy}"));
+                        }
+                        if let Some(load) = step.load {
+                            line.push_str(&format!(", load {load}"));
+                        }
+                        println!("{line}");
+                    }
+                    println!("Score: {}", result.score());
+                    for (node, window) in &missed {
+                        println!("Missed the time window {window} of {node:?}");
+                    }
+                    if let Some(georeference) = &georeference {
+                        println!("Waypoints: {:?}", georeference.waypoints(&result))
+                    }
+                }
+                Format::Json => println!("{}", Report::new(None, GridDimensions::of(&graph), &options, positions.first().copied(), &result).with_georeference(georeference).with_missed_windows(missed).to_json()),
+                Format::Csv => print!("{}", Report::new(None, GridDimensions::of(&graph), &options, positions.first().copied(), &result).with_georeference(georeference).with_missed_windows(missed).to_csv())
+            }
+        }
+        Err(error) => {
+            println!("Invalid path: {error}");
+            std::process::exit(1);
+        }
+    }
+}
+
 fn main() {
 
     let args = Args::parse();
 
-    let x = args.x.unwrap_or(0);
-    let y = args.y.unwrap_or(0);
-    let graph = Graph::from_file(&args.file);
-    let recovery_rate = args.recovery_rate.unwrap_or(1);
-    let timeout = args.timeout.unwrap_or(Duration::from_secs(2));
+    match args.command {
+        Some(Command::Verify(verify_args)) => verify(*verify_args),
+        Some(Command::Run(run_args)) => run(run_args),
+        Some(Command::Convert(convert_args)) => convert(convert_args),
+        Some(Command::Generate(generate_args)) => generate(generate_args),
+        Some(Command::Bench(bench_args)) => bench(bench_args),
+        None => plan(args)
+    }
+}
 
+/// Plans a path in a separate thread, giving up once the timeout has passed.
+fn plan_with_timeout<S: Score>(graph: Arc<Graph<S>>, planner: Planner, start: Position, options: PlanningOptions, progress: bool, timeout: Duration) -> Option<PathfindingResult<S::Total>> {
     let (tx, rx) = mpsc::channel();
 
     // Spawn the function in a separate thread
     thread::spawn(move || {
-        let result = graph.path_planning_bfs((x, y), args.max_timesteps, recovery_rate);
+        let result = if progress {
+            let mut observer = ProgressLine { max_timesteps: Simulation::new(&graph, &options).max_steps(), last_print: None };
+            let result = planner.plan(&graph, start, &options, &mut observer);
+            eprintln!();
+            result
+        } else {
+            planner.plan(&graph, start, &options, &mut NoopObserver)
+        };
         let _ = tx.send(result); // Send result through the channel
     });
 
     // Set a timeout duration
     match rx.recv_timeout(timeout) {
-        Ok(path) => {
-            println!("Path: {path:?}");
-            println!("Score: {:?}", path.score())
-        }
+        Ok(path) => Some(path),
         Err(mpsc::RecvTimeoutError::Timeout) => {
-            println!("Timed out after {:?}", timeout);
+            eprintln!("Timed out after {:?}", timeout);
+            None
         }
         Err(mpsc::RecvTimeoutError::Disconnected) => {
-            println!("Thread disconnected");
+            eprintln!("Thread disconnected");
+            None
+        }
+    }
+}
+
+fn plan(args: Args) {
+    let file = args.file.as_deref().expect("-I is required");
+    match args.values {
+        Values::Unsigned => plan_values(&args, load_grid(file, &args.image)),
+        Values::Signed => plan_values::<i64>(&args, load_values(file, &args.image)),
+        Values::Float => plan_values::<f64>(&args, load_values(file, &args.image))
+    }
+}
+
+fn plan_values<S: Score>(args: &Args, (graph, georeference): (Graph<S>, Option<Georeference>))
+where
+    S::Total: serde::Serialize
+{
+    let x = args.x.unwrap_or(0);
+    let y = args.y.unwrap_or(0);
+    let graph = args.blockages.apply(args.windows.apply(args.hopper.apply(args.energy.apply(args.costs.apply(graph, &args.image)))));
+    let grid = GridDimensions::of(&graph);
+    let recovery_rate = args.recovery_rate.unwrap_or(1);
+    let timeout = args.timeout.unwrap_or(Duration::from_secs(2));
+    let max_timesteps = args.max_timesteps.expect("-T is required");
+    let options = PlanningOptions {
+        seed: args.seed,
+        neighborhood: args.neighborhood,
+        semantics: args.semantics.semantics(),
+        penalties: args.penalties,
+        move_costs: args.costs.move_costs(),
+        footprint: args.footprint.footprint(),
+        harvest: args.harvest.harvest(),
+        energy: args.energy.budget(),
+        hopper: args.hopper.hopper(),
+        waypoints: args.waypoints.waypoints(&graph),
+        revisits: args.revisits.revisits(),
+        ..PlanningOptions::new(max_timesteps, recovery_rate)
+    };
+
+    let graph = Arc::new(graph);
+    let Some(path) = plan_with_timeout(graph.clone(), args.planner.clone(), (x, y), options.clone(), args.progress, timeout) else {
+        std::process::exit(1);
+    };
+
+    let positions: Vec<Position> = path.path.iter().map(|s| s.node).collect();
+    let missed = graph.missed_windows(&positions, &options);
+    match args.format {
+        Format::Text => {
+            println!("Seed: {}", args.seed);
+            println!("Path: {path:?}");
+            println!("Score: {:?}", path.score());
+            for (node, window) in &missed {
+                println!("Missed the {}time window {window} of {node:?}", if window.required { "required " } else { "" });
+            }
+            if let Err(VerifyError::MissedWaypoint { waypoint, node }) = graph.verify_path(&positions, &options) {
+                println!("Missed waypoint {waypoint} at {node:?}");
+            }
+            if let Some(georeference) = &georeference {
+                println!("Waypoints: {:?}", georeference.waypoints(&path))
+            }
+        }
+        Format::Json => println!("{}", Report::new(Some(&args.planner), grid, &options, Some((x, y)), &path).with_georeference(georeference).with_missed_windows(missed).to_json()),
+        Format::Csv => print!("{}", Report::new(Some(&args.planner), grid, &options, Some((x, y)), &path).with_georeference(georeference).with_missed_windows(missed).to_csv())
+    }
+}
+
+fn run(args: RunArgs) {
+    let scenario = match Scenario::from_file(&args.scenario) {
+        Ok(scenario) => scenario,
+        Err(error) => {
+            eprintln!("Unable to load scenario: {error}");
+            std::process::exit(2);
         }
+    };
+
+    let base_dir = args.scenario.parent().unwrap_or(Path::new("."));
+    let graph = match scenario.graph(base_dir) {
+        Ok(graph) => Arc::new(graph),
+        Err(error) => {
+            eprintln!("Unable to load grid: {error}");
+            std::process::exit(2);
+        }
+    };
+
+    let grid = GridDimensions::of(&graph);
+    let timeout = args.timeout.unwrap_or(Duration::from_secs(2));
+    let mut reports = Vec::new();
+
+    for &start in &scenario.starts {
+        if args.format == Format::Text {
+            println!("Start: {start:?}");
+        }
+
+        let Some(path) = plan_with_timeout(Arc::clone(&graph), scenario.planner.clone(), start, scenario.options.clone(), args.progress, timeout) else {
+            reports.push(Report::failed(Some(&scenario.planner), grid, &scenario.options, Some(start), format!("timed out after {timeout:?}")));
+            continue;
+        };
+
+        if args.format == Format::Text {
+            println!("Seed: {}", scenario.options.seed);
+            println!("Path: {path:?}");
+            println!("Score: {:?}", path.score());
+        }
+        reports.push(Report::new(Some(&scenario.planner), grid, &scenario.options, Some(start), &path));
+    }
+
+    match args.format {
+        Format::Text => (),
+        Format::Json => println!("{}", serde_json::to_string_pretty(&reports).expect("Unable to serialize reports")),
+        Format::Csv => print!("{}", Report::to_csv_table(&reports))
+    }
+
+    // Every start has a report, but the run still failed when one of them timed out
+    if reports.iter().any(|report| report.error.is_some()) {
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_positions() {
+        assert_eq!(parse_positions("1 2\n\n3,4\n 5 ,\t6\n"), Ok(vec![(1, 2), (3, 4), (5, 6)]));
+        assert_eq!(parse_positions("1 2\n1 2 3"), Err("line 2: expected two coordinates, got '1 2 3'".to_string()));
+        assert_eq!(parse_positions("\n1 x"), Err("line 2: invalid position '1 x'".to_string()));
+    }
+
+    #[test]
+    fn test_parse_windows() {
+        let windows = parse_windows("0 1 2 5\n1,1,3,4,required\n").unwrap();
+        assert_eq!(windows, vec![
+            ((0, 1), TimeWindow { open: 2, close: 5, required: false }),
+            ((1, 1), TimeWindow { open: 3, close: 4, required: true })
+        ]);
+
+        assert_eq!(parse_windows("0 1 2"), Err("line 1: expected a position, an opening and a closing timestep, got '0 1 2'".to_string()));
+        assert_eq!(parse_windows("0 1 2 5\n\n0 1 5 2"), Err("line 3: invalid time window '0 1 5 2'".to_string()));
+        assert_eq!(parse_windows("0 1 2 5 optional"), Err("line 1: expected a position, an opening and a closing timestep, got '0 1 2 5 optional'".to_string()));
+    }
+
+    #[test]
+    fn test_parse_blockages() {
+        assert_eq!(parse_blockages("0 1 2 3\n0,1,2,1,4,4\n"), Ok(vec![Blockage::cell((0, 1), 2, 3), Blockage::region((0, 1), (2, 1), 4, 4)]));
+
+        assert_eq!(parse_blockages("0 1 2 3 4"), Err("line 1: expected a position or two corners, a first and a last timestep, got '0 1 2 3 4'".to_string()));
+        assert_eq!(parse_blockages("0 1 2 3\n0 1 5 2"), Err("line 2: invalid blockage '0 1 5 2'".to_string()));
+        assert_eq!(parse_blockages("0 1 x 3"), Err("line 1: invalid blockage '0 1 x 3'".to_string()));
     }
 }
@@ -0,0 +1,235 @@
+use clap::ValueEnum;
+use pathfinding::{Georeference, Graph, NodeWindow, PathfindingResult, PathfindingStep, Planner, PlanningOptions, Position, Score, TimeWindow, Total};
+use serde::Serialize;
+
+/// How a path is written to stdout.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Format {
+    /// Human readable output
+    #[default]
+    Text,
+    /// A single JSON document with the metadata and the steps
+    Json,
+    /// One row per step, preceded by the metadata as `#` comment lines
+    Csv
+}
+
+/// A path together with everything needed to reproduce it, with scores of the total of the cell values.
+#[derive(Serialize, Debug)]
+pub struct Report<'a, T = u64> {
+    /// The planner which found the path, `None` for a verified path.
+    pub planner: Option<&'a Planner>,
+    pub parameters: &'a PlanningOptions,
+    pub start: Option<Position>,
+    pub seed: u64,
+    pub grid: GridDimensions,
+    /// Where the grid lies in the world, for grids loaded from a georeferenced raster.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub georeference: Option<Georeference>,
+    pub score: T,
+    /// The time windows of the grid the path missed.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub missed_windows: Vec<NodeWindow>,
+    /// Why no path was found from the start, like a planner which timed out.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub steps: Vec<ReportStep<T>>
+}
+
+#[derive(Serialize, Debug, Clone, Copy)]
+pub struct GridDimensions {
+    pub width: usize,
+    pub height: usize
+}
+
+impl GridDimensions {
+    pub fn of<S: Score>(graph: &Graph<S>) -> Self {
+        GridDimensions { width: graph.width(), height: graph.height() }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct ReportStep<T = u64> {
+    #[serde(flatten)]
+    pub step: PathfindingStep<T>,
+    pub cumulative_score: T,
+    /// The world coordinates of the center of the cell, for georeferenced grids.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub world: Option<(f64, f64)>
+}
+
+impl<'a, T: Total + Serialize> Report<'a, T> {
+    pub fn new(planner: Option<&'a Planner>, grid: GridDimensions, options: &'a PlanningOptions, start: Option<Position>, result: &PathfindingResult<T>) -> Self {
+        let steps = result.path
+            .iter()
+            .scan(T::default(), |total, &step| {
+                *total = total.saturating_add(step.score);
+                Some(ReportStep { step, cumulative_score: *total, world: None })
+            })
+            .collect();
+
+        Report {
+            planner,
+            parameters: options,
+            start,
+            seed: options.seed,
+            grid,
+            georeference: None,
+            score: result.score(),
+            missed_windows: Vec::new(),
+            error: None,
+            steps
+        }
+    }
+
+    /// Creates a report without steps for a start no path was found from.
+    pub fn failed(planner: Option<&'a Planner>, grid: GridDimensions, options: &'a PlanningOptions, start: Option<Position>, error: String) -> Self {
+        Report { error: Some(error), ..Report::new(planner, grid, options, start, &PathfindingResult { path: Vec::new() }) }
+    }
+
+    /// Adds the world coordinates of every step, so the path can be used as waypoints.
+    pub fn with_georeference(mut self, georeference: Option<Georeference>) -> Self {
+        if let Some(georeference) = &georeference {
+            for row in &mut self.steps {
+                row.world = georeference.to_world(row.step.node);
+            }
+        }
+        self.georeference = georeference;
+        self
+    }
+
+    /// Adds the time windows the path missed.
+    pub fn with_missed_windows(mut self, missed: Vec<(Position, TimeWindow)>) -> Self {
+        self.missed_windows = missed.into_iter().map(|(node, window)| NodeWindow { node, window }).collect();
+        self
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Unable to serialize report")
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::new();
+        self.write_parameters(&mut csv, self.start);
+        csv.push_str(&format!("# score: {}\n", self.score));
+        for NodeWindow { node: (x, y), window } in &self.missed_windows {
+            csv.push_str(&format!("# missed_window: {x} {y} {} {}{}\n", window.open, window.close, if window.required { " required" } else { "" }));
+        }
+        self.write_header(&mut csv, false);
+        self.write_steps(&mut csv, None);
+        csv
+    }
+
+    /// Writes the reports of several starts as a single table, with the parameters of the first one
+    /// and a `start` column, for reports which only differ in their start.
+    pub fn to_csv_table(reports: &[Self]) -> String {
+        let mut csv = String::new();
+        let Some(first) = reports.first() else {
+            return csv;
+        };
+
+        first.write_parameters(&mut csv, None);
+        for report in reports {
+            let (x, y) = report.start.unwrap_or_default();
+            match &report.error {
+                Some(error) => csv.push_str(&format!("# error: {x} {y} {error}\n")),
+                None => csv.push_str(&format!("# score: {x} {y} {}\n", report.score))
+            }
+        }
+        first.write_header(&mut csv, true);
+        for report in reports {
+            report.write_steps(&mut csv, report.start);
+        }
+        csv
+    }
+
+    /// Writes the metadata as `#` comment lines.
+    fn write_parameters(&self, csv: &mut String, start: Option<Position>) {
+        if let Some(planner) = self.planner {
+            csv.push_str(&format!("# planner: {planner}\n"));
+        }
+        if let Some((x, y)) = start {
+            csv.push_str(&format!("# start: {x} {y}\n"));
+        }
+        csv.push_str(&format!("# max_timesteps: {}\n", self.parameters.max_timesteps));
+        csv.push_str(&format!("# recovery_rate: {}\n", self.parameters.recovery_rate));
+        csv.push_str(&format!("# neighborhood: {}\n", self.parameters.neighborhood));
+        csv.push_str(&format!("# seed: {}\n", self.seed));
+        csv.push_str(&format!("# grid: {}x{}\n", self.grid.width, self.grid.height));
+        if let Some(georeference) = &self.georeference {
+            csv.push_str(&format!("# origin: {} {}\n", georeference.origin.0, georeference.origin.1));
+            csv.push_str(&format!("# cell_size: {} {}\n", georeference.cell_size.0, georeference.cell_size.1));
+        }
+        if let Some(energy) = &self.parameters.energy {
+            csv.push_str(&format!("# energy: {} {}\n", energy.capacity, energy.per_timestep));
+        }
+        if let Some(hopper) = &self.parameters.hopper {
+            csv.push_str(&format!("# hopper: {} {}\n", hopper.capacity, hopper.overflow));
+        }
+        if let Some(limit) = self.parameters.revisits.limit {
+            csv.push_str(&format!("# max_revisits: {limit}\n"));
+        }
+        if self.parameters.revisits.cooldown > 0 {
+            csv.push_str(&format!("# cooldown: {}\n", self.parameters.revisits.cooldown));
+        }
+        for (x, y) in &self.parameters.waypoints {
+            csv.push_str(&format!("# waypoint: {x} {y}\n"));
+        }
+    }
+
+    fn write_header(&self, csv: &mut String, start_column: bool) {
+        if start_column {
+            csv.push_str("start,");
+        }
+        csv.push_str("step,x,y,score,cumulative_score");
+        if self.parameters.energy.is_some() {
+            csv.push_str(",energy");
+        }
+        if self.parameters.hopper.is_some() {
+            csv.push_str(",load");
+        }
+        csv.push_str(if self.georeference.is_some() { ",world_x,world_y\n" } else { "\n" });
+    }
+
+    /// Writes a row per step, starting with the start as `x y` when it's given.
+    fn write_steps(&self, csv: &mut String, start: Option<Position>) {
+        for row in &self.steps {
+            if let Some((x, y)) = start {
+                csv.push_str(&format!("{x} {y},"));
+            }
+            let (x, y) = row.step.node;
+            csv.push_str(&format!("{},{x},{y},{},{}", row.step.step, row.step.score, row.cumulative_score));
+            if let Some(energy) = row.step.energy {
+                csv.push_str(&format!(",{energy}"));
+            }
+            if let Some(load) = row.step.load {
+                csv.push_str(&format!(",{load}"));
+            }
+            match row.world {
+                Some((world_x, world_y)) => csv.push_str(&format!(",{world_x},{world_y}\n")),
+                None => csv.push('\n')
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_csv_table() {
+        let graph = Graph::from_rows(&[vec![1, 2], vec![3, 4]]);
+        let (planner, options) = (Planner::default(), PlanningOptions::new(2, 1));
+        let result = graph.verify_path(&[(1, 1), (0, 1)], &options).unwrap();
+        let reports = [
+            Report::new(Some(&planner), GridDimensions::of(&graph), &options, Some((1, 1)), &result),
+            Report::failed(Some(&planner), GridDimensions::of(&graph), &options, Some((0, 0)), "timed out".to_string())
+        ];
+
+        let csv = Report::to_csv_table(&reports);
+        let lines: Vec<&str> = csv.lines().filter(|line| !line.starts_with('#')).collect();
+        assert_eq!(lines, ["start,step,x,y,score,cumulative_score", "1 1,1,1,1,4,4", "1 1,2,0,1,3,7"]);
+        assert!(csv.contains("# score: 1 1 7\n# error: 0 0 timed out\n"));
+    }
+}
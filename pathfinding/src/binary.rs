@@ -0,0 +1,396 @@
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::{Graph, GridParseError, ImageError, ImageOptions};
+use crate::cells::Cells;
+
+/// The first bytes of every binary grid file.
+const MAGIC: [u8; 4] = *b"RGRD";
+const VERSION: u8 = 1;
+/// The size of the header, which keeps the cells that follow it aligned for any cell type.
+const HEADER_LEN: usize = 16;
+
+/// How the cells of a binary grid file are stored, all cell types are little-endian.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CellType {
+    U8,
+    U16,
+    /// The only cell type a memory-mapped grid can use without copying, see [`Graph::open_binary`].
+    #[default]
+    U32
+}
+
+impl CellType {
+    /// Returns the smallest cell type which can hold the score.
+    pub fn smallest_for(score: u32) -> Self {
+        if score <= u8::MAX as u32 {
+            CellType::U8
+        } else if score <= u16::MAX as u32 {
+            CellType::U16
+        } else {
+            CellType::U32
+        }
+    }
+
+    /// Returns the number of bytes of a cell.
+    pub fn size(&self) -> usize {
+        match self {
+            CellType::U8 => 1,
+            CellType::U16 => 2,
+            CellType::U32 => 4
+        }
+    }
+
+    fn max(&self) -> u32 {
+        match self {
+            CellType::U8 => u8::MAX as u32,
+            CellType::U16 => u16::MAX as u32,
+            CellType::U32 => u32::MAX
+        }
+    }
+
+    fn from_size(size: u8) -> Option<Self> {
+        match size {
+            1 => Some(CellType::U8),
+            2 => Some(CellType::U16),
+            4 => Some(CellType::U32),
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for CellType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CellType::U8 => write!(f, "u8"),
+            CellType::U16 => write!(f, "u16"),
+            CellType::U32 => write!(f, "u32")
+        }
+    }
+}
+
+impl std::str::FromStr for CellType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "u8" => Ok(CellType::U8),
+            "u16" => Ok(CellType::U16),
+            "u32" => Ok(CellType::U32),
+            _ => Err(format!("unknown cell type '{s}', expected u8, u16 or u32"))
+        }
+    }
+}
+
+/// The reason a binary grid could not be read or written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BinaryGridError {
+    /// The bytes don't start with the magic of a binary grid.
+    InvalidMagic,
+    UnsupportedVersion(u8),
+    UnknownCellType(u8),
+    /// The number of bytes doesn't match the dimensions and cell type of the header.
+    InvalidLength { expected: usize, found: usize },
+    /// A score of the graph doesn't fit in the requested cell type.
+    ScoreTooLarge { score: u32, cell_type: CellType }
+}
+
+impl fmt::Display for BinaryGridError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BinaryGridError::InvalidMagic => write!(f, "not a binary grid"),
+            BinaryGridError::UnsupportedVersion(version) => write!(f, "unsupported binary grid version {version}"),
+            BinaryGridError::UnknownCellType(size) => write!(f, "unknown cell size of {size} bytes"),
+            BinaryGridError::InvalidLength { expected, found } => write!(f, "expected {expected} bytes, found {found}"),
+            BinaryGridError::ScoreTooLarge { score, cell_type } => write!(f, "score {score} doesn't fit in a {cell_type} cell")
+        }
+    }
+}
+
+impl Error for BinaryGridError {}
+
+/// The reason a grid file could not be opened by [`Graph::open`] or [`Graph::open_binary`].
+#[derive(Debug)]
+pub enum GridFileError {
+    Io(PathBuf, std::io::Error),
+    Text(GridParseError),
+    Binary(BinaryGridError),
+    Image(ImageError)
+}
+
+impl fmt::Display for GridFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GridFileError::Io(path, error) => write!(f, "{}: {error}", path.display()),
+            GridFileError::Text(error) => write!(f, "invalid grid: {error}"),
+            GridFileError::Binary(error) => write!(f, "invalid binary grid: {error}"),
+            GridFileError::Image(error) => write!(f, "{error}")
+        }
+    }
+}
+
+impl Error for GridFileError {}
+
+/// The dimensions and cell type of a binary grid.
+struct Header {
+    height: usize,
+    width: usize,
+    cell_type: CellType
+}
+
+impl Header {
+    fn read(bytes: &[u8]) -> Result<Header, BinaryGridError> {
+        if !Graph::is_binary_grid(bytes) {
+            return Err(BinaryGridError::InvalidMagic);
+        }
+        if bytes.len() < HEADER_LEN {
+            return Err(BinaryGridError::InvalidLength { expected: HEADER_LEN, found: bytes.len() });
+        }
+        if bytes[4] != VERSION {
+            return Err(BinaryGridError::UnsupportedVersion(bytes[4]));
+        }
+
+        let cell_type = CellType::from_size(bytes[5]).ok_or(BinaryGridError::UnknownCellType(bytes[5]))?;
+        let width = u32::from_le_bytes(bytes[8..12].try_into().unwrap()) as usize;
+        let height = u32::from_le_bytes(bytes[12..16].try_into().unwrap()) as usize;
+        let header = Header { height, width, cell_type };
+
+        // Dimensions whose size doesn't fit in a usize can't match any number of bytes
+        let expected = header.len().unwrap_or(usize::MAX);
+        if bytes.len() != expected {
+            return Err(BinaryGridError::InvalidLength { expected, found: bytes.len() });
+        }
+
+        Ok(header)
+    }
+
+    /// Returns the number of bytes of the file, header included, `None` when it overflows.
+    fn len(&self) -> Option<usize> {
+        self.height.checked_mul(self.width)?.checked_mul(self.cell_type.size())?.checked_add(HEADER_LEN)
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Vec<u32> {
+        let cells = &bytes[HEADER_LEN..];
+        match self.cell_type {
+            CellType::U8 => cells.iter().map(|&b| b as u32).collect(),
+            CellType::U16 => cells.chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]]) as u32).collect(),
+            CellType::U32 => cells.chunks_exact(4).map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]])).collect()
+        }
+    }
+}
+
+impl Graph {
+    /// Returns whether the bytes start like a binary grid.
+    pub fn is_binary_grid(bytes: &[u8]) -> bool {
+        bytes.starts_with(&MAGIC)
+    }
+
+    /// Encodes the graph in the binary grid format, which is a 16 byte header followed by the
+    /// cells in row-major order:
+    ///
+    /// | bytes  | contents                              |
+    /// |--------|---------------------------------------|
+    /// | 0..4   | the magic `RGRD`                      |
+    /// | 4      | the version of the format, 1          |
+    /// | 5      | the cell size in bytes, 1, 2 or 4     |
+    /// | 6..8   | reserved, 0                           |
+    /// | 8..12  | the width as a little-endian `u32`    |
+    /// | 12..16 | the height as a little-endian `u32`   |
+    ///
+    /// Obstacles are not part of the format, like in the text format.
+    pub fn to_binary(&self, cell_type: CellType) -> Result<Vec<u8>, BinaryGridError> {
+        let cells = self.cells.as_slice();
+
+        if let Some(&score) = cells.iter().find(|&&score| score > cell_type.max()) {
+            return Err(BinaryGridError::ScoreTooLarge { score, cell_type });
+        }
+
+        let header = Header { height: self.height, width: self.width, cell_type };
+        let mut bytes = Vec::with_capacity(header.len().unwrap_or(HEADER_LEN));
+        bytes.extend_from_slice(&MAGIC);
+        bytes.extend_from_slice(&[VERSION, cell_type.size() as u8, 0, 0]);
+        bytes.extend_from_slice(&(self.width as u32).to_le_bytes());
+        bytes.extend_from_slice(&(self.height as u32).to_le_bytes());
+
+        for &score in cells {
+            match cell_type {
+                CellType::U8 => bytes.push(score as u8),
+                CellType::U16 => bytes.extend_from_slice(&(score as u16).to_le_bytes()),
+                CellType::U32 => bytes.extend_from_slice(&score.to_le_bytes())
+            }
+        }
+
+        Ok(bytes)
+    }
+
+    /// Decodes a graph in the binary grid format, see [`Graph::to_binary`].
+    pub fn from_binary(bytes: &[u8]) -> Result<Graph, BinaryGridError> {
+        let header = Header::read(bytes)?;
+        Ok(Graph::from_cells(header.height, header.width, Cells::Owned(header.decode(bytes))))
+    }
+
+    /// Writes the graph to a file in the binary grid format, see [`Graph::to_binary`].
+    pub fn write_binary(&self, path: &Path, cell_type: CellType) -> Result<(), GridFileError> {
+        let bytes = self.to_binary(cell_type).map_err(GridFileError::Binary)?;
+        fs::write(path, bytes).map_err(|e| GridFileError::Io(path.to_path_buf(), e))
+    }
+
+    /// Opens a file in the binary grid format.
+    ///
+    /// With the `mmap` feature the file is memory-mapped, and on little-endian targets a grid of
+    /// `u32` cells is used straight from the mapping, so even huge grids open without reading or
+    /// copying them. The file should not be modified while the graph is in use. Other cell types
+    /// are widened into memory, as are the cells of a mapped graph once one of them is changed.
+    pub fn open_binary(path: &Path) -> Result<Graph, GridFileError> {
+        let io_error = |e| GridFileError::Io(path.to_path_buf(), e);
+
+        #[cfg(feature = "mmap")]
+        {
+            let file = fs::File::open(path).map_err(io_error)?;
+            // SAFETY: the mapping is read-only, the documentation asks not to modify the file while it's mapped
+            let mmap = unsafe { memmap2::Mmap::map(&file) }.map_err(io_error)?;
+            let header = Header::read(&mmap).map_err(GridFileError::Binary)?;
+
+            let cells = if header.cell_type == CellType::U32 && cfg!(target_endian = "little") {
+                Cells::Mapped { mmap: std::sync::Arc::new(mmap), offset: HEADER_LEN, len: header.height * header.width }
+            } else {
+                Cells::Owned(header.decode(&mmap))
+            };
+
+            Ok(Graph::from_cells(header.height, header.width, cells))
+        }
+
+        #[cfg(not(feature = "mmap"))]
+        {
+            let bytes = fs::read(path).map_err(io_error)?;
+            Graph::from_binary(&bytes).map_err(GridFileError::Binary)
+        }
+    }
+
+    /// Opens a grid file in the text or binary format, an ESRI ASCII grid or a PGM or PNG image,
+    /// picking the format from the first bytes of the file. Images are read with the default
+    /// [`ImageOptions`], the georeference of an ESRI ASCII grid is dropped, see
+    /// [`Graph::from_esri_ascii`] to keep it.
+    pub fn open(path: &Path) -> Result<Graph, GridFileError> {
+        Graph::open_with(path, &ImageOptions::default())
+    }
+
+    /// Opens a grid file like [`Graph::open`], reading images with the given options.
+    pub fn open_with(path: &Path, image: &ImageOptions) -> Result<Graph, GridFileError> {
+        let io_error = |e| GridFileError::Io(path.to_path_buf(), e);
+
+        let mut magic = Vec::with_capacity(MAGIC.len());
+        fs::File::open(path)
+            .and_then(|file| file.take(MAGIC.len() as u64).read_to_end(&mut magic))
+            .map_err(io_error)?;
+
+        if Graph::is_binary_grid(&magic) {
+            Graph::open_binary(path)
+        } else {
+            Graph::decode(&fs::read(path).map_err(io_error)?, image)
+        }
+    }
+
+    /// Decodes a grid in any of the formats of [`Graph::open_with`] from bytes, without memory-mapping.
+    pub fn decode(bytes: &[u8], image: &ImageOptions) -> Result<Graph, GridFileError> {
+        if Graph::is_binary_grid(bytes) {
+            Graph::from_binary(bytes).map_err(GridFileError::Binary)
+        } else if Graph::is_image(bytes) {
+            Graph::from_image(bytes, image).map_err(GridFileError::Image)
+        } else if Graph::is_esri_ascii(bytes) {
+            let contents = std::str::from_utf8(bytes).map_err(|_| GridFileError::Text(GridParseError::InvalidUtf8))?;
+            Graph::from_esri_ascii(contents).map(|(graph, _)| graph).map_err(GridFileError::Text)
+        } else {
+            Graph::try_from_bytes(bytes).map_err(GridFileError::Text)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn graph() -> Graph {
+        Graph::parse("2x3\n0 1 300\n70000 4 5").unwrap()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let graph = graph();
+        let bytes = graph.to_binary(CellType::U32).unwrap();
+
+        assert_eq!(bytes.len(), 16 + 6 * 4);
+        assert_eq!(&bytes[..16], &[b'R', b'G', b'R', b'D', 1, 4, 0, 0, 3, 0, 0, 0, 2, 0, 0, 0]);
+        assert_eq!(Graph::from_binary(&bytes).unwrap().to_rows(), graph.to_rows());
+    }
+
+    #[test]
+    fn test_small_cell_types() {
+        let graph = Graph::parse("1 2\n255 3").unwrap();
+
+        for cell_type in [CellType::U8, CellType::U16] {
+            let bytes = graph.to_binary(cell_type).unwrap();
+            assert_eq!(bytes.len(), 16 + 4 * cell_type.size());
+            assert_eq!(Graph::from_binary(&bytes).unwrap().to_rows(), graph.to_rows());
+        }
+    }
+
+    #[test]
+    fn test_score_too_large() {
+        assert_eq!(graph().to_binary(CellType::U16), Err(BinaryGridError::ScoreTooLarge { score: 70000, cell_type: CellType::U16 }));
+        assert_eq!(CellType::smallest_for(70000), CellType::U32);
+    }
+
+    #[test]
+    fn test_invalid_bytes() {
+        let bytes = graph().to_binary(CellType::U32).unwrap();
+
+        assert_eq!(Graph::from_binary(b"0 1 3").unwrap_err(), BinaryGridError::InvalidMagic);
+        assert_eq!(Graph::from_binary(&bytes[..20]).unwrap_err(), BinaryGridError::InvalidLength { expected: 40, found: 20 });
+
+        let mut version = bytes.clone();
+        version[4] = 2;
+        assert_eq!(Graph::from_binary(&version).unwrap_err(), BinaryGridError::UnsupportedVersion(2));
+
+        let mut cell_type = bytes;
+        cell_type[5] = 3;
+        assert_eq!(Graph::from_binary(&cell_type).unwrap_err(), BinaryGridError::UnknownCellType(3));
+    }
+
+    #[test]
+    fn test_overflowing_dimensions() {
+        // 2^31 x 2^31 cells of 4 bytes wrap around to 0 bytes on 64-bit targets
+        let mut bytes = Vec::from(MAGIC);
+        bytes.extend_from_slice(&[VERSION, 4, 0, 0]);
+        bytes.extend_from_slice(&(1u32 << 31).to_le_bytes());
+        bytes.extend_from_slice(&(1u32 << 31).to_le_bytes());
+
+        assert_eq!(Graph::from_binary(&bytes).unwrap_err(), BinaryGridError::InvalidLength { expected: usize::MAX, found: 16 });
+    }
+
+    #[test]
+    fn test_open_detects_format() {
+        let dir = std::env::temp_dir().join(format!("rimor-binary-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let graph = graph();
+
+        let binary = dir.join("grid.bin");
+        graph.write_binary(&binary, CellType::U32).unwrap();
+        let text = dir.join("grid.txt");
+        fs::write(&text, "2x3\n0 1 300\n70000 4 5").unwrap();
+
+        let mut opened = Graph::open(&binary).unwrap();
+        assert_eq!(opened.to_rows(), graph.to_rows());
+        assert_eq!(Graph::open(&text).unwrap().to_rows(), graph.to_rows());
+
+        // Changing a mapped graph copies it instead of writing to the file
+        opened.add_node((1, 2), 9);
+        assert_eq!(*opened.get_score_at((1, 2)), 9);
+        assert_eq!(*Graph::open_binary(&binary).unwrap().get_score_at((1, 2)), 5);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,122 @@
+use std::fmt;
+
+use crate::{Graph, Neighborhood, Position, Score};
+
+/// A cell or a rectangle of cells which is closed during an interval of timesteps, like a
+/// scheduled crossing, see [`Graph::add_blockage`].
+///
+/// A blocked cell can't be entered, and the agent can't wait in it either, on the timesteps of the
+/// interval. Outside of it the cell is like any other. A blockage which ends before it starts is
+/// never active, so scenarios reject it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Blockage {
+    /// The blocked cell, or a corner of the blocked rectangle.
+    pub node: Position,
+    /// The opposite corner of the blocked rectangle, `None` when only the node is blocked.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub corner: Option<Position>,
+    /// The first timestep the cells are blocked.
+    pub from: u32,
+    /// The last timestep the cells are blocked.
+    pub until: u32
+}
+
+impl Blockage {
+    /// Creates a blockage of a single cell.
+    pub fn cell(node: Position, from: u32, until: u32) -> Self {
+        Blockage { node, corner: None, from, until }
+    }
+
+    /// Creates a blockage of the rectangle between two opposite corners, both included.
+    pub fn region(node: Position, corner: Position, from: u32, until: u32) -> Self {
+        Blockage { node, corner: Some(corner), from, until }
+    }
+
+    /// Returns whether the cell lies inside the blocked cell or rectangle.
+    pub fn contains(&self, u: Position) -> bool {
+        let corner = self.corner.unwrap_or(self.node);
+        (self.node.0.min(corner.0)..=self.node.0.max(corner.0)).contains(&u.0) && (self.node.1.min(corner.1)..=self.node.1.max(corner.1)).contains(&u.1)
+    }
+
+    /// Returns whether the cells are blocked on the given timestep.
+    pub fn is_active(&self, timestep: u32) -> bool {
+        (self.from..=self.until).contains(&timestep)
+    }
+}
+
+impl fmt::Display for Blockage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.from, self.until)
+    }
+}
+
+impl<S: Score> Graph<S> {
+    /// Mutates the existing graph to close a cell or a rectangle of cells during an interval of timesteps.
+    pub fn add_blockage(&mut self, blockage: Blockage) {
+        self.blockages.push(blockage);
+    }
+
+    /// Returns the blockages of the graph in the order they were added.
+    pub fn blockages(&self) -> &[Blockage] {
+        &self.blockages
+    }
+
+    /// Returns whether the node at the given position is blocked on the given timestep.
+    pub fn is_blocked(&self, u: Position, timestep: u32) -> bool {
+        self.blockages.iter().any(|blockage| blockage.is_active(timestep) && blockage.contains(u))
+    }
+
+    /// Gets the neighbors of a node at the given position in the given neighborhood which can be
+    /// entered on the given timestep, skipping obstacles and blocked cells.
+    pub fn get_neighbors_at(&self, u: Position, neighborhood: Neighborhood, timestep: u32) -> Vec<Position> {
+        self.get_neighbors_in(u, neighborhood).into_iter().filter(|&v| !self.is_blocked(v, timestep)).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{NoopObserver, Planner, PlanningOptions, Simulation, VerifyError};
+
+    fn crossing() -> Graph {
+        let mut graph = Graph::from_rows(&[vec![0, 1, 1], vec![0, 1, 9], vec![0, 1, 1]]);
+        graph.add_blockage(Blockage::region((0, 1), (2, 1), 2, 3));
+        graph
+    }
+
+    #[test]
+    fn test_blocked_neighbors() {
+        let graph = crossing();
+
+        assert!(graph.is_blocked((1, 1), 2) && !graph.is_blocked((1, 1), 4));
+        assert_eq!(graph.get_neighbors_at((1, 0), Neighborhood::VonNeumann, 3), vec![(0, 0), (2, 0)]);
+        assert_eq!(graph.get_neighbors_at((1, 0), Neighborhood::VonNeumann, 4), vec![(0, 0), (1, 1), (2, 0)]);
+    }
+
+    #[test]
+    fn test_rejects_blocked_cell() {
+        let graph = crossing();
+        let options = PlanningOptions::new(5, 0);
+
+        let error = graph.verify_path(&[(1, 0), (1, 1)], &options).unwrap_err();
+        assert_eq!(error, VerifyError::Blocked { step: 2, node: (1, 1) });
+
+        // Waiting for the crossing to open
+        let mut simulation = Simulation::new(&graph, &options);
+        simulation.step((1, 0)).unwrap();
+        assert_eq!(simulation.neighbors(), vec![(0, 0), (2, 0)]);
+        assert!(graph.verify_path(&[(1, 0), (0, 0), (1, 0), (1, 1), (1, 2)], &options).is_ok());
+    }
+
+    #[test]
+    fn test_planners_avoid_blocked_cells() {
+        let graph = crossing();
+        let options = PlanningOptions::new(5, 0);
+
+        for planner in [Planner::BestFirstSearch, Planner::DepthFirstSearch] {
+            let result = planner.plan(&graph, (1, 0), &options, &mut NoopObserver);
+            assert!(result.path.iter().all(|s| !graph.is_blocked(s.node, s.step)), "{planner} entered a blocked cell");
+        }
+    }
+}
@@ -0,0 +1,42 @@
+#[cfg(feature = "mmap")]
+use std::sync::Arc;
+
+/// The scores of a graph in row-major order, either owned or borrowed from a memory-mapped grid file.
+#[derive(Debug, Clone)]
+pub(crate) enum Cells<S> {
+    Owned(Vec<S>),
+    /// The `len` cells of a binary grid file of `u32` cells, starting at byte `offset`. Only created
+    /// on little-endian targets, where the bytes of the file can be used as they are.
+    #[cfg(feature = "mmap")]
+    Mapped { mmap: Arc<memmap2::Mmap>, offset: usize, len: usize }
+}
+
+impl<S: Copy> Cells<S> {
+    pub(crate) fn as_slice(&self) -> &[S] {
+        match self {
+            Cells::Owned(cells) => cells,
+            #[cfg(feature = "mmap")]
+            Cells::Mapped { mmap, offset, len } => {
+                // SAFETY: only created for `u32` cells, where every bit pattern is valid, alignment is
+                // checked by `align_to`
+                let (prefix, cells, _) = unsafe { mmap[*offset..].align_to::<S>() };
+                debug_assert!(prefix.is_empty(), "mapped cells are not aligned");
+                &cells[..*len]
+            }
+        }
+    }
+
+    /// Returns the cells for writing, copying mapped cells into memory first.
+    pub(crate) fn to_mut(&mut self) -> &mut Vec<S> {
+        #[cfg(feature = "mmap")]
+        if let Cells::Mapped { .. } = self {
+            *self = Cells::Owned(self.as_slice().to_vec());
+        }
+
+        match self {
+            Cells::Owned(cells) => cells,
+            #[cfg(feature = "mmap")]
+            Cells::Mapped { .. } => unreachable!()
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::error::Error;
+use std::fmt;
+
+use crate::{Graph, PlanningOptions, Position, Score};
+
+/// The reason an entry cost layer was rejected by [`Graph::with_entry_costs`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EntryCostError {
+    /// The layer doesn't have the dimensions of the graph, as `(height, width)`.
+    DimensionMismatch { expected: (usize, usize), found: (usize, usize) },
+    /// A cell of the layer is 0, which would let the agent move without time passing.
+    ZeroCost { node: Position }
+}
+
+impl fmt::Display for EntryCostError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EntryCostError::DimensionMismatch { expected, found } => write!(f, "the entry costs are a {}x{} grid, expected {}x{}", found.0, found.1, expected.0, expected.1),
+            EntryCostError::ZeroCost { node } => write!(f, "the entry cost of {node:?} is 0, expected at least 1")
+        }
+    }
+}
+
+impl Error for EntryCostError {}
+
+impl<S: Score> Graph<S> {
+    /// Adds a second layer to the graph with the time it takes to enter each cell, like slow
+    /// terrain, as a multiple of the [`MoveCosts`](crate::MoveCosts) of the options.
+    ///
+    /// The layer is a grid of the same dimensions, loaded like any other grid, where every cell is at least 1.
+    pub fn with_entry_costs(mut self, costs: &Graph) -> Result<Self, EntryCostError> {
+        if (costs.height(), costs.width()) != (self.height(), self.width()) {
+            return Err(EntryCostError::DimensionMismatch { expected: (self.height(), self.width()), found: (costs.height(), costs.width()) });
+        }
+
+        let layer: Vec<u32> = costs.to_rows().into_iter().flatten().collect();
+        if let Some(index) = layer.iter().position(|&cost| cost == 0) {
+            return Err(EntryCostError::ZeroCost { node: (index / self.width(), index % self.width()) });
+        }
+
+        self.entry_costs = Some(layer);
+        Ok(self)
+    }
+
+    /// Returns whether the graph has an entry cost layer.
+    pub fn has_entry_costs(&self) -> bool {
+        self.entry_costs.is_some()
+    }
+
+    /// Returns the entry cost of the cell at the given position, 1 without an entry cost layer.
+    pub fn entry_cost(&self, u: Position) -> u32 {
+        match &self.entry_costs {
+            Some(layer) => layer[self.index(u)],
+            None => 1
+        }
+    }
+
+    /// Returns the entry costs of the graph, one list per row.
+    pub fn entry_cost_rows(&self) -> Vec<Vec<u32>> {
+        (0..self.height()).map(|i| (0..self.width()).map(|j| self.entry_cost((i, j))).collect()).collect()
+    }
+}
+
+/// Returns the fewest timesteps it takes to move from every cell to one of the targets, with a
+/// search outwards from the targets, `u64::MAX` for the cells which can't reach any.
+pub(crate) fn timesteps_to<S: Score>(graph: &Graph<S>, targets: &[Position], options: &PlanningOptions) -> Vec<u64> {
+    let mut timesteps = vec![u64::MAX; graph.height() * graph.width()];
+    let mut queue = BinaryHeap::new();
+
+    for &target in targets.iter().filter(|&&u| graph.contains(u) && !graph.is_obstacle(u)) {
+        timesteps[graph.index(target)] = 0;
+        queue.push(Reverse((0, target)));
+    }
+
+    while let Some(Reverse((time, v))) = queue.pop() {
+        if time > timesteps[graph.index(v)] {
+            continue;
+        }
+
+        // The neighborhoods are symmetric, so the cells which can move into v are its neighbors
+        for u in graph.get_neighbors_in(v, options.neighborhood) {
+            let through = time.saturating_add(graph.entry_cost(v) as u64 * options.move_costs.between(u, v) as u64);
+            if through < timesteps[graph.index(u)] {
+                timesteps[graph.index(u)] = through;
+                queue.push(Reverse((through, u)));
+            }
+        }
+    }
+
+    timesteps
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_entry_costs() {
+        let graph = Graph::new(2).with_entry_costs(&Graph::from_rows(&[vec![1, 2], vec![3, 4]])).unwrap();
+
+        assert!(graph.has_entry_costs());
+        assert_eq!(graph.entry_cost((1, 0)), 3);
+        assert_eq!(Graph::new(2).entry_cost((1, 0)), 1);
+    }
+
+    #[test]
+    fn test_rejects_invalid_layer() {
+        let error = Graph::new(2).with_entry_costs(&Graph::with_dimensions(2, 3)).unwrap_err();
+        assert_eq!(error, EntryCostError::DimensionMismatch { expected: (2, 2), found: (2, 3) });
+
+        let error = Graph::new(2).with_entry_costs(&Graph::from_rows(&[vec![1, 1], vec![1, 0]])).unwrap_err();
+        assert_eq!(error, EntryCostError::ZeroCost { node: (1, 1) });
+    }
+}
@@ -0,0 +1,97 @@
+use crate::costs::timesteps_to;
+use crate::{Graph, PlanningOptions, Position, Score, Simulation};
+
+impl<S: Score> Graph<S> {
+    /// Mutates the existing graph to mark the node at the given position as a charging station,
+    /// which refills the battery of the agent when it enters the node, see [`EnergyBudget`](crate::EnergyBudget).
+    pub fn add_charger(&mut self, u: Position) {
+        self.chargers.insert(u);
+    }
+
+    /// Returns whether the node at the given position is a charging station.
+    pub fn is_charger(&self, u: Position) -> bool {
+        self.chargers.contains(&u)
+    }
+
+    /// Returns the positions of the charging stations, row by row.
+    pub fn chargers(&self) -> Vec<Position> {
+        let mut chargers: Vec<Position> = self.chargers.iter().copied().collect();
+        chargers.sort_unstable();
+        chargers
+    }
+}
+
+/// The least energy it takes to reach a charger from every cell, which the best first search keeps
+/// in reserve so it doesn't strand the agent with an empty battery.
+pub(crate) struct Reserves {
+    energy: Vec<u64>
+}
+
+impl Reserves {
+    /// Finds the reserves with a search outwards from every charger, `None` without an energy budget.
+    pub(crate) fn new<S: Score>(graph: &Graph<S>, options: &PlanningOptions) -> Option<Self> {
+        let budget = options.energy?;
+        let energy = timesteps_to(graph, &graph.chargers(), options)
+            .into_iter()
+            .map(|timesteps| timesteps.saturating_mul(budget.per_timestep as u64))
+            .collect();
+
+        Some(Reserves { energy })
+    }
+
+    /// Returns whether the agent still has enough energy after entering the cell to reach a charger,
+    /// or to move until the time is up.
+    pub(crate) fn keeps<S: Score>(&self, simulation: &Simulation<'_, S>, u: Position) -> bool {
+        let (Some(budget), Some(left)) = (simulation.options().energy, simulation.energy_after(u)) else {
+            return true;
+        };
+
+        let remaining = simulation.max_steps().saturating_sub(simulation.arrival(u)) as u64 * budget.per_timestep as u64;
+        left as u64 >= self.energy[simulation.graph().index(u)].min(remaining)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{EnergyBudget, Neighborhood, NoopObserver, VerifyError};
+
+    fn corridor() -> Graph {
+        let mut graph = Graph::from_rows(&[vec![0, 1, 1, 1, 9]]);
+        graph.add_charger((0, 0));
+        graph
+    }
+
+    #[test]
+    fn test_reserves() {
+        let options = PlanningOptions { energy: Some(EnergyBudget::new(3)), ..PlanningOptions::new(10, 0) };
+        let reserves = Reserves::new(&corridor(), &options).unwrap();
+
+        assert_eq!(reserves.energy, vec![0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_charger_refills() {
+        let graph = corridor();
+        let options = PlanningOptions { energy: Some(EnergyBudget::new(2)), ..PlanningOptions::new(10, 0) };
+
+        let path = graph.verify_path(&[(0, 1), (0, 0), (0, 1), (0, 2)], &options).unwrap();
+        assert_eq!(path.path.iter().map(|s| s.energy).collect::<Vec<_>>(), vec![Some(2), Some(2), Some(1), Some(0)]);
+
+        // Entering the charger uses energy as well
+        let error = graph.verify_path(&[(0, 1), (0, 2), (0, 1), (0, 0)], &options).unwrap_err();
+        assert_eq!(error, VerifyError::OutOfEnergy { step: 4, node: (0, 0) });
+    }
+
+    #[test]
+    fn test_bfs_returns_to_charger() {
+        let graph = corridor();
+        let options = PlanningOptions { neighborhood: Neighborhood::VonNeumann, energy: Some(EnergyBudget::new(4)), ..PlanningOptions::new(12, 1) };
+        let result = graph.path_planning_bfs_observed((0, 0), &options, &mut NoopObserver);
+
+        // Running straight for the 9 would strand the agent there with an empty battery after 5 steps
+        assert_eq!(result.path.len(), 12);
+        assert!(result.path.iter().all(|s| s.energy.is_some()));
+        assert!(graph.verify_path(&result.path.iter().map(|s| s.node).collect::<Vec<_>>(), &options).is_ok());
+    }
+}
@@ -0,0 +1,296 @@
+use crate::{Graph, GridParseError, PathfindingResult, Position, Score};
+
+/// Where the cells of a grid lie in the world, as read from a georeferenced raster like an ESRI
+/// ASCII grid. World coordinates grow to the east and to the north, while the first row of the
+/// grid is its northern edge.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Georeference {
+    /// The world coordinates of the lower left corner of the grid.
+    pub origin: (f64, f64),
+    /// The size of a cell in world units, as `(width, height)`.
+    pub cell_size: (f64, f64),
+    /// The number of rows and columns of the grid, as `(height, width)`.
+    pub dimensions: (usize, usize)
+}
+
+impl Georeference {
+    /// Returns the world coordinates of the center of the cell, or `None` if it lies outside of the grid.
+    pub fn to_world(&self, u: Position) -> Option<(f64, f64)> {
+        let (rows, columns) = self.dimensions;
+        if u.0 >= rows || u.1 >= columns {
+            return None;
+        }
+
+        let x = self.origin.0 + (u.1 as f64 + 0.5) * self.cell_size.0;
+        let y = self.origin.1 + ((rows - u.0) as f64 - 0.5) * self.cell_size.1;
+        Some((x, y))
+    }
+
+    /// Returns the cell which contains the world coordinates, or `None` if they lie outside of the grid.
+    pub fn to_position(&self, (x, y): (f64, f64)) -> Option<Position> {
+        let (rows, columns) = self.dimensions;
+        let column = ((x - self.origin.0) / self.cell_size.0).floor();
+        let row_from_bottom = ((y - self.origin.1) / self.cell_size.1).floor();
+
+        if column < 0.0 || row_from_bottom < 0.0 || column >= columns as f64 || row_from_bottom >= rows as f64 {
+            return None;
+        }
+
+        Some((rows - 1 - row_from_bottom as usize, column as usize))
+    }
+
+    /// Returns the world coordinates of the cells of the path, one waypoint per step inside the grid.
+    pub fn waypoints<T>(&self, result: &PathfindingResult<T>) -> Vec<(f64, f64)> {
+        result.path.iter().filter_map(|step| self.to_world(step.node)).collect()
+    }
+}
+
+/// Returns the key and the value of a header line like `cellsize 25.0`.
+fn header_line(line: &str) -> Option<(String, &str)> {
+    let (key, value) = line.split_once(char::is_whitespace)?;
+    key.starts_with(|c: char| c.is_ascii_alphabetic()).then(|| (key.to_ascii_lowercase(), value.trim()))
+}
+
+impl Graph {
+    /// Returns whether the bytes start like an ESRI ASCII grid.
+    pub fn is_esri_ascii(bytes: &[u8]) -> bool {
+        let bytes = bytes.strip_prefix("\u{feff}".as_bytes()).unwrap_or(bytes);
+        let start = bytes.iter().position(|b| !b.is_ascii_whitespace()).unwrap_or(bytes.len());
+        bytes[start..].get(..5).is_some_and(|key| key.eq_ignore_ascii_case(b"ncols"))
+    }
+
+    /// Parses an ESRI ASCII grid, the `.asc` files exported by most GIS tools, keeping where the
+    /// grid lies in the world.
+    ///
+    /// The header gives `ncols`, `nrows`, the lower left corner as `xllcorner` and `yllcorner` or
+    /// the center of that cell as `xllcenter` and `yllcenter`, the positive `cellsize` (or `dx`
+    /// and `dy`) and optionally the `NODATA_value`. Each of the following lines is a row,
+    /// starting with the northern one. Values are rounded to the nearest score, negative values
+    /// become 0, and cells with the NODATA value become obstacles. Use
+    /// [`Graph::from_esri_ascii_values`] to keep negative or fractional values.
+    pub fn from_esri_ascii(contents: &str) -> Result<(Graph, Georeference), GridParseError> {
+        Graph::from_esri_ascii_values(contents)
+    }
+}
+
+impl<S: Score> Graph<S> {
+    /// Parses an ESRI ASCII grid like [`Graph::from_esri_ascii`], converting the values with
+    /// [`Score::from_f64`], so an `f64` graph keeps them as they are.
+    pub fn from_esri_ascii_values(contents: &str) -> Result<(Graph<S>, Georeference), GridParseError> {
+        let contents = contents.strip_prefix('\u{feff}').unwrap_or(contents);
+        let mut lines = contents
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty())
+            .peekable();
+
+        let mut columns = None;
+        let mut rows = None;
+        let mut corner = (None, None);
+        let mut center = (None, None);
+        let mut cell_size = (None, None);
+        let mut nodata = None;
+
+        while let Some(&(line_number, line)) = lines.peek() {
+            let Some((key, value)) = header_line(line) else {
+                break;
+            };
+            lines.next();
+
+            let invalid = || GridParseError::InvalidHeader { line: line_number, token: line.to_string() };
+            let number = || value.parse::<f64>().ok().filter(|x| x.is_finite()).ok_or_else(invalid);
+            let size = || number().and_then(|x| if x > 0.0 { Ok(x) } else { Err(invalid()) });
+            let count = || value.parse::<usize>().map_err(|_| invalid());
+
+            match key.as_str() {
+                "ncols" => columns = Some(count()?),
+                "nrows" => rows = Some(count()?),
+                "xllcorner" => corner.0 = Some(number()?),
+                "yllcorner" => corner.1 = Some(number()?),
+                "xllcenter" => center.0 = Some(number()?),
+                "yllcenter" => center.1 = Some(number()?),
+                "cellsize" => cell_size = (Some(size()?), Some(size()?)),
+                "dx" => cell_size.0 = Some(size()?),
+                "dy" => cell_size.1 = Some(size()?),
+                "nodata_value" => nodata = Some(number()?),
+                _ => return Err(invalid())
+            }
+        }
+
+        let columns = columns.ok_or(GridParseError::MissingHeader("ncols"))?;
+        let rows = rows.ok_or(GridParseError::MissingHeader("nrows"))?;
+        let cell_size = (
+            cell_size.0.ok_or(GridParseError::MissingHeader("cellsize"))?,
+            cell_size.1.ok_or(GridParseError::MissingHeader("cellsize"))?
+        );
+        let origin = (
+            corner.0.or(center.0.map(|x| x - cell_size.0 / 2.0)).ok_or(GridParseError::MissingHeader("xllcorner"))?,
+            corner.1.or(center.1.map(|y| y - cell_size.1 / 2.0)).ok_or(GridParseError::MissingHeader("yllcorner"))?
+        );
+
+        // Checks the size against the cells given before allocating the grid
+        let body: Vec<(usize, &str)> = lines.collect();
+        let cells: usize = body.iter().map(|(_, line)| line.split_whitespace().count()).sum();
+        if rows.checked_mul(columns).filter(|&count| count <= cells).is_none() {
+            return Err(GridParseError::DimensionMismatch { expected: (rows, columns), found: (body.len(), columns) });
+        }
+
+        let mut graph = Graph::zeros(rows, columns);
+        let mut row = 0;
+
+        for (line_number, line) in body {
+            if row == rows {
+                return Err(GridParseError::DimensionMismatch { expected: (rows, columns), found: (row + 1, columns) });
+            }
+
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            if tokens.len() != columns {
+                return Err(GridParseError::RaggedRow { line: line_number, expected: columns, found: tokens.len() });
+            }
+
+            for (column, token) in tokens.into_iter().enumerate() {
+                let value = token
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|x| x.is_finite())
+                    .ok_or_else(|| GridParseError::InvalidScore { line: line_number, column: column + 1, token: token.to_string() })?;
+
+                if nodata == Some(value) {
+                    graph.add_obstacle((row, column));
+                } else {
+                    graph.add_node((row, column), S::from_f64(value));
+                }
+            }
+
+            row += 1;
+        }
+
+        if row != rows {
+            return Err(GridParseError::DimensionMismatch { expected: (rows, columns), found: (row, columns) });
+        }
+
+        Ok((graph, Georeference { origin, cell_size, dimensions: (rows, columns) }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::PathfindingStep;
+
+    const ASC: &str = "ncols 3
+nrows 2
+xllcorner 1000.0
+yllcorner 2000.0
+cellsize 10
+NODATA_value -9999
+1.4 2.6 -9999
+-3 0 7
+";
+
+    #[test]
+    fn test_parse() {
+        let (graph, georeference) = Graph::from_esri_ascii(ASC).unwrap();
+
+        assert_eq!(graph.to_rows(), vec![vec![1, 3, 0], vec![0, 0, 7]]);
+        assert!(graph.is_obstacle((0, 2)));
+        assert!(!graph.is_obstacle((1, 0)));
+        assert_eq!(georeference, Georeference { origin: (1000.0, 2000.0), cell_size: (10.0, 10.0), dimensions: (2, 3) });
+    }
+
+    #[test]
+    fn test_keeps_values() {
+        let (graph, _) = Graph::<f64>::from_esri_ascii_values(ASC).unwrap();
+        assert_eq!(graph.to_rows(), vec![vec![1.4, 2.6, 0.0], vec![-3.0, 0.0, 7.0]]);
+
+        let (graph, _) = Graph::<i64>::from_esri_ascii_values(ASC).unwrap();
+        assert_eq!(graph.to_rows(), vec![vec![1, 3, 0], vec![-3, 0, 7]]);
+    }
+
+    #[test]
+    fn test_center_header() {
+        let asc = ASC.replace("xllcorner 1000.0", "XLLCENTER 1005.0").replace("yllcorner 2000.0", "yllcenter 2005");
+        let (_, georeference) = Graph::from_esri_ascii(&asc).unwrap();
+
+        assert_eq!(georeference.origin, (1000.0, 2000.0));
+    }
+
+    #[test]
+    fn test_world_coordinates() {
+        let (_, georeference) = Graph::from_esri_ascii(ASC).unwrap();
+
+        // The first row is the northern one
+        assert_eq!(georeference.to_world((0, 0)), Some((1005.0, 2015.0)));
+        assert_eq!(georeference.to_world((1, 2)), Some((1025.0, 2005.0)));
+        assert_eq!(georeference.to_world((5, 0)), None);
+        assert_eq!(georeference.to_world((0, 3)), None);
+
+        assert_eq!(georeference.to_position((1005.0, 2015.0)), Some((0, 0)));
+        assert_eq!(georeference.to_position((1029.9, 2000.0)), Some((1, 2)));
+        assert_eq!(georeference.to_position((1030.0, 2000.0)), None);
+        assert_eq!(georeference.to_position((999.0, 2005.0)), None);
+
+        for i in 0..2 {
+            for j in 0..3 {
+                assert_eq!(georeference.to_position(georeference.to_world((i, j)).unwrap()), Some((i, j)));
+            }
+        }
+    }
+
+    #[test]
+    fn test_waypoints() {
+        let (_, georeference) = Graph::from_esri_ascii(ASC).unwrap();
+        let result = PathfindingResult {
+            path: vec![
+                PathfindingStep { node: (1, 0), score: 0, step: 1, energy: None, load: None },
+                PathfindingStep { node: (0, 1), score: 3, step: 2, energy: None, load: None }
+            ]
+        };
+
+        assert_eq!(georeference.waypoints(&result), vec![(1005.0, 2005.0), (1015.0, 2015.0)]);
+    }
+
+    #[test]
+    fn test_missing_header() {
+        assert_eq!(Graph::from_esri_ascii("ncols 1\nnrows 1\nxllcorner 0\nyllcorner 0\n1").unwrap_err(), GridParseError::MissingHeader("cellsize"));
+    }
+
+    #[test]
+    fn test_invalid_cell_size() {
+        for cellsize in ["cellsize 0", "cellsize -10", "cellsize NaN", "dx inf"] {
+            let asc = ASC.replace("cellsize 10", &format!("cellsize 10\n{cellsize}"));
+            assert_eq!(Graph::from_esri_ascii(&asc).unwrap_err(), GridParseError::InvalidHeader { line: 6, token: cellsize.to_string() });
+        }
+    }
+
+    #[test]
+    fn test_row_count() {
+        let asc = ASC.replace("-3 0 7\n", "");
+        assert_eq!(Graph::from_esri_ascii(&asc).unwrap_err(), GridParseError::DimensionMismatch { expected: (2, 3), found: (1, 3) });
+    }
+
+    #[test]
+    fn test_overflowing_dimensions() {
+        let asc = "ncols 100000000000\nnrows 100000000000\nxllcorner 0\nyllcorner 0\ncellsize 1\n1 2";
+        let expected = GridParseError::DimensionMismatch { expected: (100000000000, 100000000000), found: (1, 100000000000) };
+        assert_eq!(Graph::from_esri_ascii(asc).unwrap_err(), expected);
+    }
+
+    #[test]
+    fn test_non_finite_values() {
+        for token in ["nan", "inf", "-inf"] {
+            let asc = ASC.replace("-3 0 7", &format!("-3 {token} 7"));
+            let expected = GridParseError::InvalidScore { line: 8, column: 2, token: token.to_string() };
+            assert_eq!(Graph::<f64>::from_esri_ascii_values(&asc).unwrap_err(), expected);
+        }
+    }
+
+    #[test]
+    fn test_detects_format() {
+        assert!(Graph::is_esri_ascii(ASC.as_bytes()));
+        assert!(Graph::is_esri_ascii(b"\n  NCOLS 3"));
+        assert!(!Graph::is_esri_ascii(b"0 1 3"));
+    }
+}
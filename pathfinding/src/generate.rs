@@ -0,0 +1,227 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{Graph, SplitMix64};
+
+/// How the scores of a generated grid are spread over its cells.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "name", rename_all = "kebab-case"))]
+pub enum Distribution {
+    /// Every score is drawn independently from the value range.
+    Uniform,
+    /// Gaussian bumps around randomly placed centers, peaking at the maximum of the value range.
+    Hotspots { count: usize, radius: f64 },
+    /// Smooth value noise, with features roughly `scale` cells apart.
+    Noise { scale: f64 },
+    /// Scores rising from the top left corner to the bottom right corner.
+    Gradient,
+    /// Alternating squares of `tile` by `tile` cells with the minimum and maximum score.
+    Checkerboard { tile: usize },
+    /// The minimum score everywhere, except for a fraction of `density` cells with the maximum score.
+    Treasure { density: f64 }
+}
+
+impl Distribution {
+    /// Returns the name of the distribution, as accepted by [`Distribution::from_str`].
+    pub fn name(&self) -> &'static str {
+        match self {
+            Distribution::Uniform => "uniform",
+            Distribution::Hotspots { .. } => "hotspots",
+            Distribution::Noise { .. } => "noise",
+            Distribution::Gradient => "gradient",
+            Distribution::Checkerboard { .. } => "checkerboard",
+            Distribution::Treasure { .. } => "treasure"
+        }
+    }
+}
+
+impl fmt::Display for Distribution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Parses the name of a distribution, using default parameters.
+impl FromStr for Distribution {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "uniform" => Ok(Distribution::Uniform),
+            "hotspots" => Ok(Distribution::Hotspots { count: 3, radius: 3.0 }),
+            "noise" => Ok(Distribution::Noise { scale: 8.0 }),
+            "gradient" => Ok(Distribution::Gradient),
+            "checkerboard" => Ok(Distribution::Checkerboard { tile: 1 }),
+            "treasure" => Ok(Distribution::Treasure { density: 0.05 }),
+            _ => Err(format!("Unknown distribution '{s}', expected 'uniform', 'hotspots', 'noise', 'gradient', 'checkerboard' or 'treasure'"))
+        }
+    }
+}
+
+/// Generates reproducible grids, the same generator always produces the same grid.
+///
+/// ```
+/// use pathfinding::{Distribution, GridGenerator};
+///
+/// let graph = GridGenerator { seed: 42, max: 9, ..GridGenerator::new(20, 30, Distribution::Uniform) }.generate();
+/// assert_eq!((graph.height(), graph.width()), (20, 30));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GridGenerator {
+    pub height: usize,
+    pub width: usize,
+    /// The lowest score of a cell.
+    pub min: u32,
+    /// The highest score of a cell.
+    pub max: u32,
+    pub seed: u64,
+    pub distribution: Distribution
+}
+
+impl GridGenerator {
+    /// Creates a generator of grids with scores from 0 to 9 and seed 0.
+    pub fn new(height: usize, width: usize, distribution: Distribution) -> Self {
+        GridGenerator { height, width, min: 0, max: 9, seed: 0, distribution }
+    }
+
+    /// Generates the grid.
+    pub fn generate(&self) -> Graph {
+        let mut rng = SplitMix64::new(self.seed);
+        let values = match &self.distribution {
+            Distribution::Uniform => self.cells(|_, _| rng.next_f64()),
+            Distribution::Hotspots { count, radius } => self.hotspots(&mut rng, *count, *radius),
+            Distribution::Noise { scale } => self.cells(|i, j| value_noise(self.seed, i as f64 / scale, j as f64 / scale)),
+            Distribution::Gradient => self.cells(|i, j| (fraction(i, self.height) + fraction(j, self.width)) / 2.0),
+            Distribution::Checkerboard { tile } => {
+                let tile = (*tile).max(1);
+                self.cells(|i, j| ((i / tile + j / tile) % 2) as f64)
+            }
+            Distribution::Treasure { density } => self.cells(|_, _| if rng.next_f64() < *density { 1.0 } else { 0.0 })
+        };
+
+        let (min, max) = (self.min.min(self.max), self.min.max(self.max));
+        let rows: Vec<Vec<u32>> = values
+            .into_iter()
+            .map(|row| row.into_iter().map(|v| min + (v.clamp(0.0, 1.0) * (max - min) as f64).round() as u32).collect())
+            .collect();
+
+        Graph::from_rows(&rows)
+    }
+
+    /// Returns a value between 0 and 1 for every cell, row by row.
+    fn cells(&self, mut value: impl FnMut(usize, usize) -> f64) -> Vec<Vec<f64>> {
+        (0..self.height).map(|i| (0..self.width).map(|j| value(i, j)).collect()).collect()
+    }
+
+    fn hotspots(&self, rng: &mut SplitMix64, count: usize, radius: f64) -> Vec<Vec<f64>> {
+        let centers: Vec<(f64, f64)> = (0..count)
+            .map(|_| (rng.next_f64() * self.height as f64, rng.next_f64() * self.width as f64))
+            .collect();
+
+        let values = self.cells(|i, j| {
+            centers
+                .iter()
+                .map(|(ci, cj)| {
+                    let distance = (i as f64 - ci).powi(2) + (j as f64 - cj).powi(2);
+                    (-distance / (2.0 * radius * radius)).exp()
+                })
+                .sum()
+        });
+
+        // Scaled so the highest cell gets the maximum score, even where hotspots overlap
+        let peak = values.iter().flatten().copied().fold(0.0, f64::max);
+        if peak > 0.0 {
+            values.into_iter().map(|row| row.into_iter().map(|v| v / peak).collect()).collect()
+        } else {
+            values
+        }
+    }
+}
+
+/// Returns the position of `i` between 0 and `n - 1` as a value between 0 and 1.
+fn fraction(i: usize, n: usize) -> f64 {
+    if n > 1 { i as f64 / (n - 1) as f64 } else { 0.0 }
+}
+
+/// Returns a random value between 0 and 1 for a point of the noise lattice.
+fn lattice(seed: u64, i: i64, j: i64) -> f64 {
+    let hash = (i as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15) ^ (j as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F);
+    SplitMix64::new(seed ^ hash).next_f64()
+}
+
+/// Interpolates the random values of the lattice points around `(x, y)`, smoothing the transitions.
+fn value_noise(seed: u64, x: f64, y: f64) -> f64 {
+    let (i, j) = (x.floor() as i64, y.floor() as i64);
+    let smooth = |t: f64| t * t * (3.0 - 2.0 * t);
+    let (tx, ty) = (smooth(x - x.floor()), smooth(y - y.floor()));
+
+    let top = lattice(seed, i, j) * (1.0 - ty) + lattice(seed, i, j + 1) * ty;
+    let bottom = lattice(seed, i + 1, j) * (1.0 - ty) + lattice(seed, i + 1, j + 1) * ty;
+    top * (1.0 - tx) + bottom * tx
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn distributions() -> Vec<Distribution> {
+        ["uniform", "hotspots", "noise", "gradient", "checkerboard", "treasure"]
+            .iter()
+            .map(|name| name.parse().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_same_seed_same_grid() {
+        for distribution in distributions() {
+            let generator = GridGenerator { seed: 7, ..GridGenerator::new(12, 8, distribution) };
+            assert_eq!(generator.generate().to_rows(), generator.generate().to_rows());
+        }
+    }
+
+    #[test]
+    fn test_seed_changes_grid() {
+        for distribution in [Distribution::Uniform, Distribution::Noise { scale: 4.0 }, Distribution::Hotspots { count: 2, radius: 2.0 }] {
+            let first = GridGenerator { seed: 1, max: 100, ..GridGenerator::new(16, 16, distribution.clone()) }.generate();
+            let second = GridGenerator { seed: 2, max: 100, ..GridGenerator::new(16, 16, distribution) }.generate();
+            assert_ne!(first.to_rows(), second.to_rows());
+        }
+    }
+
+    #[test]
+    fn test_value_range() {
+        for distribution in distributions() {
+            let graph = GridGenerator { min: 5, max: 20, seed: 3, ..GridGenerator::new(10, 10, distribution) }.generate();
+            assert!(graph.to_rows().iter().flatten().all(|&score| (5..=20).contains(&score)));
+        }
+    }
+
+    #[test]
+    fn test_checkerboard() {
+        let graph = GridGenerator { min: 1, max: 5, ..GridGenerator::new(3, 4, Distribution::Checkerboard { tile: 2 }) }.generate();
+        assert_eq!(graph.to_rows(), vec![vec![1, 1, 5, 5], vec![1, 1, 5, 5], vec![5, 5, 1, 1]]);
+    }
+
+    #[test]
+    fn test_gradient() {
+        let graph = GridGenerator { max: 10, ..GridGenerator::new(3, 3, Distribution::Gradient) }.generate();
+        assert_eq!(graph.to_rows(), vec![vec![0, 3, 5], vec![3, 5, 8], vec![5, 8, 10]]);
+    }
+
+    #[test]
+    fn test_hotspots_peak() {
+        let graph = GridGenerator { max: 50, seed: 11, ..GridGenerator::new(20, 20, Distribution::Hotspots { count: 2, radius: 2.0 }) }.generate();
+        assert_eq!(graph.to_rows().iter().flatten().max(), Some(&50));
+    }
+
+    #[test]
+    fn test_treasure_is_sparse() {
+        let graph = GridGenerator { max: 100, seed: 5, ..GridGenerator::new(50, 50, Distribution::Treasure { density: 0.1 }) }.generate();
+        let treasures = graph.to_rows().iter().flatten().filter(|&&score| score == 100).count();
+
+        assert!((150..350).contains(&treasures), "{treasures} treasures");
+        assert!(graph.to_rows().iter().flatten().all(|&score| score == 0 || score == 100));
+    }
+}
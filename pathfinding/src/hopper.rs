@@ -0,0 +1,115 @@
+use crate::costs::timesteps_to;
+use crate::{Graph, PlanningOptions, Position, Score, Simulation, Total};
+
+impl<S: Score> Graph<S> {
+    /// Mutates the existing graph to mark the node at the given position as a depot, which empties
+    /// the hopper of the agent when it enters the node, see [`Hopper`](crate::Hopper).
+    pub fn add_depot(&mut self, u: Position) {
+        self.depots.insert(u);
+    }
+
+    /// Returns whether the node at the given position is a depot.
+    pub fn is_depot(&self, u: Position) -> bool {
+        self.depots.contains(&u)
+    }
+
+    /// Returns the positions of the depots, row by row.
+    pub fn depots(&self) -> Vec<Position> {
+        let mut depots: Vec<Position> = self.depots.iter().copied().collect();
+        depots.sort_unstable();
+        depots
+    }
+}
+
+/// The fewest timesteps it takes to reach a depot from every cell, which the best first search uses
+/// to bring the load back before it is lost.
+pub(crate) struct Returns {
+    timesteps: Vec<u64>
+}
+
+impl Returns {
+    /// Finds the timesteps with a search outwards from every depot, `None` without a hopper.
+    pub(crate) fn new<S: Score>(graph: &Graph<S>, options: &PlanningOptions) -> Option<Self> {
+        options.hopper?;
+        Some(Returns { timesteps: timesteps_to(graph, &graph.depots(), options) })
+    }
+
+    /// Returns whether the agent can still unload at a depot before the time is up after entering
+    /// the cell, and whether it heads for a depot when its hopper is full.
+    pub(crate) fn keeps<S: Score>(&self, simulation: &Simulation<'_, S>, u: Position) -> bool {
+        let (Some(hopper), Some(load), Some(from)) = (simulation.options().hopper, simulation.load_after(u), simulation.position()) else {
+            return true;
+        };
+        if load <= S::Total::default() {
+            return true;
+        }
+
+        let graph = simulation.graph();
+        let timesteps = self.timesteps[graph.index(u)];
+        let full = S::Total::from_recovery(hopper.capacity as u64).is_some_and(|capacity| load >= capacity);
+
+        (simulation.arrival(u) as u64).saturating_add(timesteps) <= simulation.max_steps() as u64
+            && (!full || timesteps < self.timesteps[graph.index(from)])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Hopper, NoopObserver, Overflow, TimeWindow};
+
+    fn field() -> Graph {
+        let mut graph = Graph::from_rows(&[vec![0, 2, 3, 4]]);
+        graph.add_depot((0, 0));
+        graph
+    }
+
+    #[test]
+    fn test_unloads_at_depot() {
+        let graph = field();
+        let options = PlanningOptions { hopper: Some(Hopper::new(5)), ..PlanningOptions::new(10, 0) };
+
+        // The 4 no longer fits and is lost, and only the score unloaded at the end counts
+        let path = graph.verify_path(&[(0, 0), (0, 1), (0, 2), (0, 3), (0, 2), (0, 1), (0, 0)], &options).unwrap();
+        assert_eq!(path.path.iter().map(|s| (s.score, s.load)).collect::<Vec<_>>(), vec![
+            (0, Some(0)), (0, Some(2)), (0, Some(5)), (0, Some(5)), (0, Some(5)), (0, Some(5)), (5, Some(0))
+        ]);
+        assert_eq!(path.score(), 5);
+    }
+
+    #[test]
+    fn test_blocked_overflow() {
+        let graph = field();
+        let options = PlanningOptions { hopper: Some(Hopper { capacity: 5, overflow: Overflow::Blocked }), ..PlanningOptions::new(10, 0) };
+        let mut simulation = Simulation::new(&graph, &options);
+
+        for u in [(0, 0), (0, 1), (0, 2)] {
+            simulation.step(u).unwrap();
+        }
+
+        // The 4 doesn't fit next to the 2 and 3, so it stays in the cell
+        assert_eq!(simulation.step((0, 3)).unwrap().load, Some(5));
+        assert_eq!(simulation.cell_score_at((0, 3)), 4);
+    }
+
+    #[test]
+    fn test_blocked_overflow_meets_window() {
+        let mut graph = Graph::from_rows(&[vec![0, 5, 5]]);
+        graph.add_window((0, 2), TimeWindow { open: 1, close: 5, required: true });
+        let options = PlanningOptions { hopper: Some(Hopper { capacity: 5, overflow: Overflow::Blocked }), ..PlanningOptions::new(10, 0) };
+
+        // The second 5 doesn't fit, but the cell was still entered inside its window
+        let path = graph.verify_path(&[(0, 0), (0, 1), (0, 2)], &options).unwrap();
+        assert_eq!(path.path.last().unwrap().load, Some(5));
+    }
+
+    #[test]
+    fn test_bfs_returns_to_depot() {
+        let graph = field();
+        let options = PlanningOptions { hopper: Some(Hopper::new(5)), ..PlanningOptions::new(8, 1) };
+        let result = graph.path_planning_bfs_observed((0, 0), &options, &mut NoopObserver);
+
+        assert!(result.path.iter().skip(1).any(|s| s.node == (0, 0)));
+        assert!(result.score() > 0);
+    }
+}
@@ -0,0 +1,357 @@
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::Graph;
+
+/// Which value of a pixel becomes the score of a cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Channel {
+    /// The brightness of the pixel as `0.299 R + 0.587 G + 0.114 B`, the gray value of a grayscale image.
+    #[default]
+    Luminance,
+    Red,
+    Green,
+    Blue,
+    /// The opacity of the pixel, the maximum value for images without transparency.
+    Alpha
+}
+
+impl fmt::Display for Channel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Channel::Luminance => write!(f, "luminance"),
+            Channel::Red => write!(f, "red"),
+            Channel::Green => write!(f, "green"),
+            Channel::Blue => write!(f, "blue"),
+            Channel::Alpha => write!(f, "alpha")
+        }
+    }
+}
+
+impl FromStr for Channel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "luminance" | "l" => Ok(Channel::Luminance),
+            "red" | "r" => Ok(Channel::Red),
+            "green" | "g" => Ok(Channel::Green),
+            "blue" | "b" => Ok(Channel::Blue),
+            "alpha" | "a" => Ok(Channel::Alpha),
+            _ => Err(format!("unknown channel '{s}', expected luminance, red, green, blue or alpha"))
+        }
+    }
+}
+
+/// How an image is turned into a graph by [`Graph::from_image`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageOptions {
+    pub channel: Channel,
+    /// The score of a cell is `value * scale + offset`, rounded and clamped to the range of a score.
+    pub scale: f64,
+    pub offset: f64,
+    /// The number of rows and columns of the graph, as `(height, width)`, each cell gets the
+    /// average value of the pixels it covers. `None` keeps one cell per pixel.
+    pub size: Option<(usize, usize)>
+}
+
+impl Default for ImageOptions {
+    fn default() -> Self {
+        ImageOptions { channel: Channel::Luminance, scale: 1.0, offset: 0.0, size: None }
+    }
+}
+
+/// The reason an image could not be turned into a graph.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImageError {
+    /// The bytes are neither a PGM nor a PNG image.
+    UnknownFormat,
+    InvalidPgm(String),
+    InvalidPng(String),
+    /// The image is a PNG, but the `png` feature is not enabled.
+    PngUnsupported,
+    /// The requested size is empty or larger than the image.
+    InvalidSize { size: (usize, usize), image: (usize, usize) }
+}
+
+impl fmt::Display for ImageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImageError::UnknownFormat => write!(f, "not a PGM or PNG image"),
+            ImageError::InvalidPgm(error) => write!(f, "invalid PGM image: {error}"),
+            ImageError::InvalidPng(error) => write!(f, "invalid PNG image: {error}"),
+            ImageError::PngUnsupported => write!(f, "PNG images require the `png` feature"),
+            ImageError::InvalidSize { size, image } => write!(f, "can't downsample a {}x{} image to {}x{}", image.0, image.1, size.0, size.1)
+        }
+    }
+}
+
+impl Error for ImageError {}
+
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+/// The pixels of an image as RGBA values, row by row.
+struct Pixels {
+    height: usize,
+    width: usize,
+    /// The channels of the pixels, transparency is the maximum value when the image has none.
+    rgba: Vec<[u16; 4]>
+}
+
+impl Pixels {
+    fn value(&self, pixel: [u16; 4], channel: Channel) -> f64 {
+        let [r, g, b, a] = pixel.map(f64::from);
+        match channel {
+            Channel::Luminance => 0.299 * r + 0.587 * g + 0.114 * b,
+            Channel::Red => r,
+            Channel::Green => g,
+            Channel::Blue => b,
+            Channel::Alpha => a
+        }
+    }
+
+    /// Returns the values of the channel, averaged over the pixels covered by each of the cells.
+    fn downsample(&self, channel: Channel, (height, width): (usize, usize)) -> Vec<Vec<f64>> {
+        (0..height)
+            .map(|i| {
+                let rows = i * self.height / height..(i + 1) * self.height / height;
+                (0..width)
+                    .map(|j| {
+                        let columns = j * self.width / width..(j + 1) * self.width / width;
+                        let count = rows.len() * columns.len();
+                        let sum: f64 = rows
+                            .clone()
+                            .flat_map(|y| columns.clone().map(move |x| y * self.width + x))
+                            .map(|p| self.value(self.rgba[p], channel))
+                            .sum();
+                        sum / count as f64
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// Reads a binary (`P5`) or plain (`P2`) PGM image. Samples keep their value, whatever the maximum.
+fn read_pgm(bytes: &[u8]) -> Result<Pixels, ImageError> {
+    let invalid = |message: &str| ImageError::InvalidPgm(message.to_string());
+
+    // The header is four whitespace separated tokens, with comments running to the end of the line
+    let mut position = 2;
+    let mut header = [0usize; 3];
+    for value in header.iter_mut() {
+        loop {
+            match bytes.get(position) {
+                Some(b) if b.is_ascii_whitespace() => position += 1,
+                Some(b'#') => while bytes.get(position).is_some_and(|&b| b != b'\n') {
+                    position += 1;
+                },
+                _ => break
+            }
+        }
+        let start = position;
+        while bytes.get(position).is_some_and(u8::is_ascii_digit) {
+            position += 1;
+        }
+        *value = std::str::from_utf8(&bytes[start..position])
+            .ok()
+            .and_then(|token| token.parse().ok())
+            .ok_or_else(|| invalid("expected the width, height and maximum value"))?;
+    }
+
+    let [width, height, max] = header;
+    if max == 0 || max > u16::MAX as usize {
+        return Err(invalid("the maximum value should be between 1 and 65535"));
+    }
+
+    // Every sample takes at least a byte, which also rules out a count which overflows
+    let count = width.checked_mul(height).filter(|&count| count <= bytes.len()).ok_or_else(|| invalid("the width and height don't fit the image"))?;
+
+    // A single whitespace character separates the header from the samples
+    let samples: Vec<u16> = match &bytes[..2] {
+        b"P5" => {
+            let raster = bytes.get(position + 1..).unwrap_or_default();
+            if max < 256 {
+                raster.iter().map(|&b| b as u16).collect()
+            } else {
+                raster.chunks_exact(2).map(|c| u16::from_be_bytes([c[0], c[1]])).collect()
+            }
+        }
+        _ => std::str::from_utf8(&bytes[position..])
+            .map_err(|_| invalid("the samples are not ASCII"))?
+            .split_ascii_whitespace()
+            .map(|token| token.parse().map_err(|_| ImageError::InvalidPgm(format!("'{token}' is not a valid sample"))))
+            .collect::<Result<_, _>>()?
+    };
+
+    if samples.len() < count {
+        return Err(ImageError::InvalidPgm(format!("expected {count} samples, found {}", samples.len())));
+    }
+
+    let rgba = samples[..count].iter().map(|&v| [v, v, v, max as u16]).collect();
+    Ok(Pixels { height, width, rgba })
+}
+
+#[cfg(feature = "png")]
+fn read_png(bytes: &[u8]) -> Result<Pixels, ImageError> {
+    use png::{BitDepth, ColorType, Transformations};
+
+    let invalid = |e: png::DecodingError| ImageError::InvalidPng(e.to_string());
+
+    let mut decoder = png::Decoder::new(bytes);
+    // Palettes and bit depths below 8 are expanded, 16 bit samples are kept as they are
+    decoder.set_transformations(Transformations::EXPAND);
+    let mut reader = decoder.read_info().map_err(invalid)?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer).map_err(invalid)?;
+
+    let samples: Vec<u16> = match info.bit_depth {
+        BitDepth::Sixteen => buffer[..info.buffer_size()].chunks_exact(2).map(|c| u16::from_be_bytes([c[0], c[1]])).collect(),
+        _ => buffer[..info.buffer_size()].iter().map(|&b| b as u16).collect()
+    };
+    let max = match info.bit_depth {
+        BitDepth::Sixteen => u16::MAX,
+        _ => u8::MAX as u16
+    };
+
+    let rgba = match info.color_type {
+        ColorType::Grayscale => samples.iter().map(|&v| [v, v, v, max]).collect(),
+        ColorType::GrayscaleAlpha => samples.chunks_exact(2).map(|c| [c[0], c[0], c[0], c[1]]).collect(),
+        ColorType::Rgb => samples.chunks_exact(3).map(|c| [c[0], c[1], c[2], max]).collect(),
+        ColorType::Rgba => samples.chunks_exact(4).map(|c| [c[0], c[1], c[2], c[3]]).collect(),
+        ColorType::Indexed => return Err(ImageError::InvalidPng("the palette could not be expanded".to_string()))
+    };
+
+    Ok(Pixels { height: info.height as usize, width: info.width as usize, rgba })
+}
+
+#[cfg(not(feature = "png"))]
+fn read_png(_bytes: &[u8]) -> Result<Pixels, ImageError> {
+    Err(ImageError::PngUnsupported)
+}
+
+impl Graph {
+    /// Returns whether the bytes start like a PGM or PNG image.
+    pub fn is_image(bytes: &[u8]) -> bool {
+        bytes.starts_with(&PNG_SIGNATURE) || bytes.starts_with(b"P2") || bytes.starts_with(b"P5")
+    }
+
+    /// Creates a graph from a grayscale or color PGM or PNG image, like a heatmap, where every
+    /// pixel becomes a cell. PNG images require the `png` feature.
+    ///
+    /// The score of a cell is the value of the chosen channel, `0..=255` for 8 bit images and
+    /// `0..=65535` for 16 bit images, then scaled and offset by the options. Transparent pixels
+    /// are not treated as obstacles.
+    pub fn from_image(bytes: &[u8], options: &ImageOptions) -> Result<Graph, ImageError> {
+        let pixels = if bytes.starts_with(&PNG_SIGNATURE) {
+            read_png(bytes)?
+        } else if bytes.starts_with(b"P2") || bytes.starts_with(b"P5") {
+            read_pgm(bytes)?
+        } else {
+            return Err(ImageError::UnknownFormat);
+        };
+
+        let image = (pixels.height, pixels.width);
+        let size = options.size.unwrap_or(image);
+        if size.0 == 0 || size.1 == 0 || size.0 > image.0 || size.1 > image.1 {
+            return Err(ImageError::InvalidSize { size, image });
+        }
+
+        let rows: Vec<Vec<u32>> = pixels
+            .downsample(options.channel, size)
+            .into_iter()
+            .map(|row| row.into_iter().map(|value| (value * options.scale + options.offset).round().clamp(0.0, u32::MAX as f64) as u32).collect())
+            .collect();
+
+        Ok(Graph::from_rows(&rows))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const PGM: &[u8] = b"P2\n# a heatmap\n3 2\n255\n0 10 20\n30 40 255\n";
+
+    #[test]
+    fn test_plain_pgm() {
+        let graph = Graph::from_image(PGM, &ImageOptions::default()).unwrap();
+        assert_eq!(graph.to_rows(), vec![vec![0, 10, 20], vec![30, 40, 255]]);
+    }
+
+    #[test]
+    fn test_binary_pgm() {
+        let mut bytes = b"P5 2 2 65535\n".to_vec();
+        for value in [0u16, 1, 300, 65535] {
+            bytes.extend_from_slice(&value.to_be_bytes());
+        }
+
+        let graph = Graph::from_image(&bytes, &ImageOptions::default()).unwrap();
+        assert_eq!(graph.to_rows(), vec![vec![0, 1], vec![300, 65535]]);
+    }
+
+    #[test]
+    fn test_scale_and_offset() {
+        let options = ImageOptions { scale: 0.5, offset: -5.0, ..ImageOptions::default() };
+        let graph = Graph::from_image(PGM, &options).unwrap();
+
+        // Negative scores are clamped to 0
+        assert_eq!(graph.to_rows(), vec![vec![0, 0, 5], vec![10, 15, 123]]);
+    }
+
+    #[test]
+    fn test_downsample() {
+        let bytes = b"P2 4 2 255\n0 2 10 20\n4 6 30 40\n";
+        let options = ImageOptions { size: Some((1, 2)), ..ImageOptions::default() };
+
+        assert_eq!(Graph::from_image(bytes, &options).unwrap().to_rows(), vec![vec![3, 25]]);
+    }
+
+    #[test]
+    fn test_invalid_size() {
+        let options = ImageOptions { size: Some((3, 3)), ..ImageOptions::default() };
+        assert_eq!(Graph::from_image(PGM, &options).unwrap_err(), ImageError::InvalidSize { size: (3, 3), image: (2, 3) });
+    }
+
+    #[test]
+    fn test_truncated_pgm() {
+        assert!(matches!(Graph::from_image(b"P2 3 2 255\n0 1 2", &ImageOptions::default()), Err(ImageError::InvalidPgm(_))));
+    }
+
+    #[test]
+    fn test_overflowing_pgm_size() {
+        let error = Graph::from_image(b"P2\n4294967296 4294967297\n255\n1 2 3\n", &ImageOptions::default());
+        assert!(matches!(error, Err(ImageError::InvalidPgm(_))));
+        assert_eq!(Graph::from_image(b"0 1 2", &ImageOptions::default()).unwrap_err(), ImageError::UnknownFormat);
+    }
+
+    #[cfg(feature = "png")]
+    fn encode_png(color_type: png::ColorType, data: &[u8]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let mut encoder = png::Encoder::new(&mut bytes, 2, 1);
+        encoder.set_color(color_type);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header().unwrap().write_image_data(data).unwrap();
+        bytes
+    }
+
+    #[cfg(feature = "png")]
+    #[test]
+    fn test_grayscale_png() {
+        let bytes = encode_png(png::ColorType::Grayscale, &[7, 200]);
+        assert_eq!(Graph::from_image(&bytes, &ImageOptions::default()).unwrap().to_rows(), vec![vec![7, 200]]);
+    }
+
+    #[cfg(feature = "png")]
+    #[test]
+    fn test_png_channels() {
+        let bytes = encode_png(png::ColorType::Rgba, &[100, 0, 0, 255, 0, 100, 50, 10]);
+        let channel = |channel| Graph::from_image(&bytes, &ImageOptions { channel, ..ImageOptions::default() }).unwrap().to_rows();
+
+        assert_eq!(channel(Channel::Luminance), vec![vec![30, 64]]);
+        assert_eq!(channel(Channel::Red), vec![vec![100, 0]]);
+        assert_eq!(channel(Channel::Blue), vec![vec![0, 50]]);
+        assert_eq!(channel(Channel::Alpha), vec![vec![255, 10]]);
+    }
+}
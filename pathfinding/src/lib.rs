@@ -1,125 +1,285 @@
-use std::collections::BinaryHeap;
+use std::collections::{BinaryHeap, HashMap, HashSet};
 use std::fs::read;
 use std::path::Path;
 
+mod binary;
+mod blockages;
+mod cells;
+mod costs;
+mod energy;
+mod esri;
+mod generate;
+mod hopper;
+mod image;
+mod observer;
+mod oracle;
+mod options;
+mod parse;
+mod planner;
+mod rng;
+#[cfg(feature = "scenario")]
+mod scenario;
+mod score;
+mod simulation;
+mod verify;
+mod waypoints;
+mod windows;
+
+pub use binary::{BinaryGridError, CellType, GridFileError};
+pub use blockages::Blockage;
+pub use costs::EntryCostError;
+pub use esri::Georeference;
+pub use generate::{Distribution, GridGenerator};
+pub use image::{Channel, ImageError, ImageOptions};
+pub use observer::{NoopObserver, PathfindingEvent, PathfindingObserver};
+pub use oracle::BruteForceResult;
+pub use options::{EnergyBudget, Footprint, Harvest, Hopper, Horizon, MoveCosts, Neighborhood, Overflow, Penalties, PlanningOptions, RecoveryOrder, Revisits, StartCell, TimestepSemantics};
+pub use parse::GridParseError;
+pub use planner::Planner;
+#[cfg(feature = "scenario")]
+pub use scenario::{GridSource, NodeWindow, Scenario, ScenarioError, ScenarioFormat};
+pub use score::{Score, Total};
+pub use simulation::{Simulation, SimulationSnapshot};
+pub use verify::VerifyError;
+pub use windows::TimeWindow;
+
+use cells::Cells;
+use energy::Reserves;
+use hopper::Returns;
+use waypoints::Route;
+use windows::Deadlines;
+use rng::SplitMix64;
+
 pub type Position = (usize, usize);
 
-#[derive(Debug, Clone, Copy)]
-pub struct PathfindingStep {
+/// A step of a path, with the score it collected as a [`Score::Total`], `u64` for the default `u32` cells.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PathfindingStep<T = u64> {
     pub node: Position,
-    pub score: u32,
-    pub step: u32
+    pub score: T,
+    /// The timestep the agent entered the node on, which is the number of the step when every
+    /// move takes one timestep.
+    pub step: u32,
+    /// The energy left after the step, `None` without an [`EnergyBudget`].
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub energy: Option<u32>,
+    /// The load in the hopper after the step, `None` without a [`Hopper`].
+    #[cfg_attr(feature = "serde", serde(default = "Option::default", skip_serializing_if = "Option::is_none"))]
+    pub load: Option<T>
 }
 
-#[derive(Debug, Clone)]
-pub struct PathfindingResult {
-    pub path: Vec<PathfindingStep>
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PathfindingResult<T = u64> {
+    pub path: Vec<PathfindingStep<T>>
 }
 
-impl PathfindingResult {
-    pub fn empty() -> PathfindingResult {
+impl<T: Total> PathfindingResult<T> {
+    pub fn empty() -> PathfindingResult<T> {
         PathfindingResult { path: Vec::new() }
     }
 
-    pub fn steps_at(&self, node: Position) -> Vec<&PathfindingStep> {
+    pub fn steps_at(&self, node: Position) -> Vec<&PathfindingStep<T>> {
         self.path.iter().filter(|&&n| n.node == node).collect()
     }
 
-    pub fn score(&self) -> u32 {
-        self.path.iter().map(|x| x.score).sum()
+    /// Returns the total score of the path, saturating at the bounds of the total.
+    ///
+    /// A path built by a planner, the verifier or a [`Simulation`] never overflows, see
+    /// [`PathfindingResult::checked_score`] for paths put together elsewhere.
+    pub fn score(&self) -> T {
+        self.path.iter().fold(T::default(), |total, x| total.saturating_add(x.score))
+    }
+
+    /// Returns the total score of the path, or [`VerifyError::ScoreOverflow`] with the first step
+    /// where it doesn't fit in the total.
+    pub fn checked_score(&self) -> Result<T, VerifyError> {
+        self.path.iter().zip(1..).try_fold(T::default(), |total, (x, step)| total.checked_add(x.score).ok_or(VerifyError::ScoreOverflow { step }))
     }
 }
 
 #[derive(Debug)]
-struct PathfindingBestFirstSearchState {
-    pub score: u32,
-    pub timesteps_remaining: u32,
+struct PathfindingBestFirstSearchState<T> {
+    pub score: T,
+    pub tiebreak: u64,
     pub node: Position
 }
 
-impl Ord for PathfindingBestFirstSearchState {
+impl<T: Total> Ord for PathfindingBestFirstSearchState<T> {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        other.score.partial_cmp(&self.score).unwrap().reverse()
+        // Scores are never NaN, every total is finite
+        let score = self.score.partial_cmp(&other.score).unwrap_or(std::cmp::Ordering::Equal);
+        score.then(self.tiebreak.cmp(&other.tiebreak))
     }
 }
 
-impl PartialOrd for PathfindingBestFirstSearchState {
+impl<T: Total> PartialOrd for PathfindingBestFirstSearchState<T> {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
         Some(self.cmp(other))
     }
 }
 
-impl PartialEq for PathfindingBestFirstSearchState {
+impl<T: Total> PartialEq for PathfindingBestFirstSearchState<T> {
     fn eq(&self, other: &Self) -> bool {
-        self.score == other.score
+        self.cmp(other) == std::cmp::Ordering::Equal
     }
 }
 
-impl Eq for PathfindingBestFirstSearchState {}
+impl<T: Total> Eq for PathfindingBestFirstSearchState<T> {}
 
 
-#[derive(Clone)]
-pub struct Graph {
-    nodes: Vec<Vec<u32>>,
-    size: usize
+/// A grid of cells with a [`Score`], `u32` unless another type is given.
+#[derive(Debug, Clone)]
+pub struct Graph<S = u32> {
+    cells: Cells<S>,
+    /// The time it takes to enter each cell, see [`Graph::with_entry_costs`].
+    entry_costs: Option<Vec<u32>>,
+    obstacles: HashSet<Position>,
+    chargers: HashSet<Position>,
+    depots: HashSet<Position>,
+    /// The time windows of the cells which have one, see [`Graph::add_window`].
+    windows: HashMap<Position, TimeWindow>,
+    /// The cells which are closed during an interval of timesteps, see [`Graph::add_blockage`].
+    blockages: Vec<Blockage>,
+    height: usize,
+    width: usize
 }
 
 impl Graph {
     /// Creates a new graph with the given size.
     pub fn new(size: usize) -> Self {
-        Graph {
-            nodes: vec![vec![0; size]; size],
-            size: size
-        }
+        Graph::with_dimensions(size, size)
     }
 
-    /// Mutates the existing graph to add a node with the given score.
-    pub fn add_node(&mut self, u: Position, score: u32) {
-        self.nodes[u.0][u.1] = score;
+    /// Creates a new graph with the given number of rows and columns.
+    pub fn with_dimensions(height: usize, width: usize) -> Self {
+        Graph::zeros(height, width)
     }
 
-    /// Returns the score of the node at the given position.
-    pub fn get_score_at(&self, u: Position) -> &u32 {
-        &self.nodes[u.0][u.1]
+    /// Loads a graph from a file, see [`Graph::parse`] for the format.
+    pub fn from_file(path: &Path) -> Self {
+        Graph::from_bytes(read(path).expect("Unable to load file"))
     }
 
-    /// Resets the score of the node at the given position to 0.
-    pub fn reset_score(&self, u: Position) -> Graph {
+    /// Loads a graph from a byte array, see [`Graph::parse`] for the format.
+    pub fn from_bytes(bytes: Vec<u8>) -> Self {
+        Graph::try_from_bytes(&bytes).expect("Unable to parse grid")
+    }
+
+    /// Creates a graph from the scores of its rows, which should all have the same length.
+    pub fn from_rows(rows: &[Vec<u32>]) -> Self {
+        Graph::from_values(rows)
+    }
+
+    /// Recovers the graph for a given recovery rate, except for the given position.
+    ///
+    /// Cells are stored as `u32`, so a score saturates at `u32::MAX`. Use a [`Simulation`], which
+    /// scores in `u64`, to follow the scores of a path exactly.
+    pub fn recover_for(&self, recovery_rate: u32, except: Position) -> Graph {
         let mut graph = self.clone();
-        graph.nodes[u.0][u.1] = 0;
+
+        for i in 0..self.height {
+            for j in 0..self.width {
+                if (i, j) != except {
+                    graph.add_node((i, j), self.get_score_at((i, j)).saturating_add(recovery_rate));
+                }
+            }
+        }
         graph
     }
+}
 
-    /// Loads a graph from a file.
-    pub fn from_file(path: &Path) -> Self {
-        Graph::from_bytes(read(path).expect("Unable to load file"))
+impl<S: Score> Graph<S> {
+    /// Creates a graph with the given number of rows and columns, where every cell is 0.
+    pub fn zeros(height: usize, width: usize) -> Self {
+        Graph::from_cells(height, width, Cells::Owned(vec![S::default(); height * width]))
     }
 
-    /// Loads a graph from a byte array.
-    pub fn from_bytes(bytes: Vec<u8>) -> Self {
-        let contents = String::from_utf8(bytes).expect("Unable to convert bytes to string");
-        let lines = contents.lines().enumerate();
-        let grid_size = lines.clone().count();
-        let mut graph = Graph::new(grid_size);
-        for (i, line) in lines {
-            for (j, c) in line.split(" ").enumerate() {
-                graph.add_node((i, j), c.parse().expect("Unable to parse integer"));
+    fn from_cells(height: usize, width: usize, cells: Cells<S>) -> Self {
+        Graph { cells, entry_costs: None, obstacles: HashSet::new(), chargers: HashSet::new(), depots: HashSet::new(), windows: HashMap::new(), blockages: Vec::new(), height, width }
+    }
+
+    /// Creates a graph from the values of its rows, which should all have the same length, like
+    /// [`Graph::from_rows`] for any [`Score`].
+    pub fn from_values(rows: &[Vec<S>]) -> Self {
+        let mut graph = Graph::zeros(rows.len(), rows.first().map_or(0, |row| row.len()));
+        for (i, row) in rows.iter().enumerate() {
+            for (j, &score) in row.iter().enumerate() {
+                graph.add_node((i, j), score);
             }
         }
         graph
     }
 
+    /// Mutates the existing graph to add a node with the given score.
+    pub fn add_node(&mut self, u: Position, score: S) {
+        let index = self.index(u);
+        self.cells.to_mut()[index] = score;
+    }
+
+    /// Mutates the existing graph to mark the node at the given position as an obstacle.
+    pub fn add_obstacle(&mut self, u: Position) {
+        self.obstacles.insert(u);
+    }
+
+    /// Returns whether the node at the given position is an obstacle.
+    pub fn is_obstacle(&self, u: Position) -> bool {
+        self.obstacles.contains(&u)
+    }
+
+    /// Returns the positions of the obstacles, row by row.
+    pub fn obstacles(&self) -> Vec<Position> {
+        let mut obstacles: Vec<Position> = self.obstacles.iter().copied().collect();
+        obstacles.sort_unstable();
+        obstacles
+    }
+
+    /// Returns whether the given position lies within the graph.
+    pub fn contains(&self, u: Position) -> bool {
+        u.0 < self.height && u.1 < self.width
+    }
+
+    /// Returns the score of the node at the given position.
+    pub fn get_score_at(&self, u: Position) -> &S {
+        &self.cells.as_slice()[self.index(u)]
+    }
+
+    fn index(&self, u: Position) -> usize {
+        assert!(self.contains(u), "{u:?} is outside of the graph");
+        u.0 * self.width + u.1
+    }
+
+    /// Resets the score of the node at the given position to 0.
+    pub fn reset_score(&self, u: Position) -> Graph<S> {
+        let mut graph = self.clone();
+        graph.add_node(u, S::default());
+        graph
+    }
+
+    /// Returns the scores of the graph, one list per row.
+    pub fn to_rows(&self) -> Vec<Vec<S>> {
+        if self.width == 0 {
+            return vec![Vec::new(); self.height];
+        }
+        self.cells.as_slice().chunks(self.width).map(<[S]>::to_vec).collect()
+    }
+
     /// Gets the neighbors of a node at the given position.
     pub fn get_neighbors(&self, u: Position) -> Vec<(usize, usize)> {
+        self.get_neighbors_in(u, Neighborhood::Moore)
+    }
+
+    /// Gets the neighbors of a node at the given position in the given neighborhood, skipping obstacles.
+    pub fn get_neighbors_in(&self, u: Position, neighborhood: Neighborhood) -> Vec<(usize, usize)> {
         let (i, j) = u;
-        let size = self.size;
         let mut neighbors = Vec::new();
 
-        for (di, dj) in [(-1, -1), (-1, 0), (-1, 1), (0, -1), (0, 1), (1, -1), (1, 0), (1, 1)].iter() {
+        for (di, dj) in neighborhood.offsets().iter() {
             let ni = i as isize + di;
             let nj = j as isize + dj;
 
-            if ni >= 0 && ni < size as isize && nj >= 0 && nj < size as isize {
+            if ni >= 0 && ni < self.height as isize && nj >= 0 && nj < self.width as isize && !self.is_obstacle((ni as usize, nj as usize)) {
                 neighbors.push((ni as usize, nj as usize));
             }
         }
@@ -127,80 +287,296 @@ impl Graph {
         neighbors
     }
 
-    /// Returns the size of the graph.
+    /// Returns the size of the graph, which is its number of rows.
+    ///
+    /// Use [`Graph::height`] and [`Graph::width`] for graphs which are not square.
     pub fn size(&self) -> usize {
-        self.size
+        self.height
     }
 
-    /// Recovers the graph for a given recovery rate, except for the given position.
-    pub fn recover_for(&self, recovery_rate: u32, except: Position) -> Graph {
-        let mut graph = self.clone();
+    /// Returns the number of rows of the graph.
+    pub fn height(&self) -> usize {
+        self.height
+    }
 
-        for i in 0..self.size() {
-            for j in 0..self.size() {
-                if (i, j) != except {
-                    graph.nodes[i][j] += recovery_rate;
-                }
-            }
-        }
-        graph
+    /// Returns the number of columns of the graph.
+    pub fn width(&self) -> usize {
+        self.width
     }
 
     /// Does a best first search for a path from the start position with a maximum number of timesteps.
-    pub fn path_planning_bfs(&self, start: Position, max_timesteps: u32, recovery_rate: u32) -> PathfindingResult {
-        let mut pq = BinaryHeap::new();
-        let mut path = Vec::new();
-        let mut graph = self.clone();
-        let mut score = 0;
-        let mut step = 0;
+    pub fn path_planning_bfs(&self, start: Position, max_timesteps: u32, recovery_rate: u32) -> PathfindingResult<S::Total> {
+        self.path_planning_bfs_observed(start, &PlanningOptions::new(max_timesteps, recovery_rate), &mut NoopObserver)
+    }
 
-        pq.push(PathfindingBestFirstSearchState {
-            score: graph.get_score_at(start).clone(),
-            timesteps_remaining: max_timesteps,
-            node: start
-        });
+    /// Does a best first search like [`Graph::path_planning_bfs`] and reports its progress to the observer.
+    ///
+    /// Ties between equally scored neighbors are broken randomly using the seed of the options.
+    /// Neighbors which cost points are treated according to the [`Penalties`] of the options. When
+    /// moves take more than one timestep, the neighbors are ranked by their score per timestep. With
+    /// an energy budget, the neighbors from which a charger can still be reached are preferred. With a
+    /// hopper, the neighbors are also ranked by the score they add to the load, and the neighbors
+    /// from which a depot can be reached in time are preferred, heading back once the hopper is full.
+    /// The neighbors from which the required time windows can be reached before they close are
+    /// preferred as well, and so are the neighbors from which the waypoints can be passed in order
+    /// in time. When the path still misses a waypoint, it is planned again segment by segment,
+    /// taking a quickest way from one waypoint to the next before searching greedily again.
+    pub fn path_planning_bfs_observed<O: PathfindingObserver<S::Total>>(&self, start: Position, options: &PlanningOptions, observer: &mut O) -> PathfindingResult<S::Total> {
+        let mut rng = SplitMix64::new(options.seed);
+        let mut simulation = Simulation::new(self, options);
+        let guides = Guides::new(self, options);
+
+        if enter(&mut simulation, start, observer) {
+            best_first(&mut simulation, &guides, &mut rng, observer);
+        }
 
-        while let Some(state) = pq.pop() {
+        // Plans segment by segment when the greedy path missed a waypoint, taking a quickest way to
+        // every waypoint in turn and searching greedily again after the last one
+        if let Some(route) = &guides.route && !simulation.meets_waypoints() {
+            let mut fallback = Simulation::new(self, options);
+            let mut next = Some(start);
+            while let Some(node) = next && !fallback.meets_waypoints() && enter(&mut fallback, node, observer) {
+                next = route.towards(&fallback);
+            }
 
-            if state.timesteps_remaining == 0 {
-                break;
+            if fallback.meets_waypoints() {
+                best_first(&mut fallback, &guides, &mut rng, observer);
+                simulation = fallback;
             }
+        }
+
+        simulation.into_result()
+    }
 
-            let score_ = graph.get_score_at(state.node);
+    /// Does a depth first search for the path with the highest possible score from the start position.
+    pub fn path_planning_dfs(&self, start: Position, max_timesteps: u32, recovery_rate: u32) -> PathfindingResult<S::Total> {
+        self.path_planning_dfs_observed(start, &PlanningOptions::new(max_timesteps, recovery_rate), &mut NoopObserver)
+    }
+
+    /// Does a depth first search like [`Graph::path_planning_dfs`] and reports its progress to the observer.
+    ///
+    /// Every move sequence is explored, except for the branches which can't beat the best path found
+    /// so far even if every remaining timestep collected the highest score possible at that timestep. The
+    /// result is exact, but the running time grows exponentially with the number of timesteps. Of the
+    /// paths with the highest score, the first one in the order of the neighborhood is returned. When
+    /// the [`Penalties`] of the options let a path end early, every prefix of a path is a candidate.
+    /// A path which meets every required [`TimeWindow`] and passes the waypoints of the options in
+    /// order beats any path which doesn't.
+    pub fn path_planning_dfs_observed<O: PathfindingObserver<S::Total>>(&self, start: Position, options: &PlanningOptions, observer: &mut O) -> PathfindingResult<S::Total> {
+        let mut simulation = Simulation::new(self, options);
+
+        let Ok(step) = simulation.step(start) else {
+            return simulation.into_result();
+        };
+        observer.on_event(PathfindingEvent::NodeExpanded { node: start, step: step.step });
+
+        // A visited cell recovers from 0, so no step collects less than 0 plus its recovery
+        let max_score = (0..self.height)
+            .flat_map(|i| (0..self.width).map(move |j| (i, j)))
+            .filter(|&u| !self.is_obstacle(u))
+            .map(|u| self.get_score_at(u).to_total())
+            .fold(S::Total::default(), |max, score| if score > max { score } else { max });
+
+        let mut search = DepthFirstSearch { best: None, max_score, footprint: options.footprint.offsets().len() as u32, route: Route::new(self, options) };
+        search.explore(&mut simulation, observer);
+
+        if let Some((best, _)) = &search.best {
+            simulation.restore(best);
+        }
+        simulation.into_result()
+    }
+}
 
-            score += score_;
-            step += 1;
+/// The estimates which steer the best first search towards the neighbors that keep the energy
+/// budget, the hopper, the required time windows and the waypoints of the options within reach.
+struct Guides {
+    reserves: Option<Reserves>,
+    returns: Option<Returns>,
+    deadlines: Option<Deadlines>,
+    route: Option<Route>
+}
 
-            path.push(PathfindingStep { node: state.node, score: *score_, step: step });
+impl Guides {
+    fn new<S: Score>(graph: &Graph<S>, options: &PlanningOptions) -> Self {
+        Guides {
+            reserves: Reserves::new(graph, options),
+            returns: Returns::new(graph, options),
+            deadlines: Deadlines::new(graph, options),
+            route: Route::new(graph, options)
+        }
+    }
 
-            graph = graph
-                .reset_score(state.node)
-                .recover_for(recovery_rate, state.node);
+    /// Narrows the neighbors down to the ones every guide keeps, one guide after the other, skipping
+    /// a guide which keeps none of them.
+    fn retain<S: Score>(&self, simulation: &Simulation<'_, S>, neighbors: &mut Vec<(Position, S::Total)>) {
+        if let Some(reserves) = &self.reserves {
+            prefer(neighbors, |u| reserves.keeps(simulation, u));
+        }
+        if let Some(returns) = &self.returns {
+            prefer(neighbors, |u| returns.keeps(simulation, u));
+        }
+        if let Some(deadlines) = &self.deadlines {
+            prefer(neighbors, |u| deadlines.keeps(simulation, u));
+        }
+        if let Some(route) = &self.route {
+            prefer(neighbors, |u| route.keeps(simulation, u));
+        }
+    }
+}
+
+/// Keeps the neighbors for which `keeps` holds, unless it holds for none of them.
+fn prefer<T>(neighbors: &mut Vec<(Position, T)>, keeps: impl Fn(Position) -> bool) {
+    if neighbors.iter().any(|&(u, _)| keeps(u)) {
+        neighbors.retain(|&(u, _)| keeps(u));
+    }
+}
+
+/// Moves the agent into the cell and reports it to the observer, returning whether the move was allowed.
+fn enter<S: Score, O: PathfindingObserver<S::Total>>(simulation: &mut Simulation<'_, S>, u: Position, observer: &mut O) -> bool {
+    let Ok(step) = simulation.step(u) else {
+        return false;
+    };
+
+    observer.on_event(PathfindingEvent::NodeExpanded { node: u, step: step.step });
+    observer.on_event(PathfindingEvent::IncumbentImproved { path: simulation.path(), score: simulation.score() });
+    observer.on_event(PathfindingEvent::IterationFinished { iteration: step.step, score: simulation.score() });
+    true
+}
 
+/// Moves the agent greedily into the best ranked neighbor until the time is up or no neighbor is left.
+fn best_first<S: Score, O: PathfindingObserver<S::Total>>(simulation: &mut Simulation<'_, S>, guides: &Guides, rng: &mut SplitMix64, observer: &mut O) {
+    let mut pq = BinaryHeap::new();
 
-            pq.clear();
+    while !simulation.is_finished() {
+        pq.clear();
 
-            for neighbor in graph.get_neighbors(state.node) {
-                let neighbor_score = graph.get_score_at(neighbor);
+        let mut neighbors: Vec<(Position, S::Total)> = simulation.neighbors().into_iter().map(|u| (u, simulation.score_at(u).saturating_add(loaded(simulation, u)))).collect();
+        guides.retain(simulation, &mut neighbors);
+        let costly = neighbors.iter().all(|&(_, score)| score < S::Total::default());
 
-                pq.push(PathfindingBestFirstSearchState {
-                    node: neighbor,
-                    score: *neighbor_score,
-                    timesteps_remaining: state.timesteps_remaining - 1,
-                });
+        for (neighbor, score) in neighbors {
+            let rank = match simulation.options().penalties {
+                _ if !costly => Some(score),
+                Penalties::Accept => Some(score),
+                Penalties::Avoid => None,
+                Penalties::LookAhead => Some(score.saturating_add(best_next(simulation, neighbor))).filter(|&gain| gain > S::Total::default())
+            };
+
+            if let Some(score) = rank {
+                let score = score.per(simulation.duration(neighbor));
+                pq.push(PathfindingBestFirstSearchState { node: neighbor, score, tiebreak: rng.next_u64() });
             }
         }
 
-        PathfindingResult { path }
+        // Every candidate is a neighbor from the simulation, which is only rejected when the score overflows
+        let Some(state) = pq.pop() else {
+            break;
+        };
+        if !enter(simulation, state.node, observer) {
+            break;
+        }
     }
+}
 
-    pub fn path_planning_dfs(&self, start: Position, max_timesteps: u32, recovery_rate: u32) -> PathfindingResult {
+/// Returns the score entering the cell adds to the hopper, 0 without a hopper or when it is unloaded.
+fn loaded<S: Score>(simulation: &Simulation<'_, S>, u: Position) -> S::Total {
+    match (simulation.load(), simulation.load_after(u)) {
+        (Some(load), Some(after)) if after > load => after.saturating_sub(load),
+        _ => S::Total::default()
+    }
+}
 
-        unimplemented!()
+/// Returns the highest score the agent could collect on the step after entering the neighbor, 0
+/// when the path ends there.
+fn best_next<S: Score>(simulation: &mut Simulation<'_, S>, neighbor: Position) -> S::Total {
+    let snapshot = simulation.snapshot();
+    let mut best = S::Total::default();
+
+    if simulation.step(neighbor).is_ok() && !simulation.is_finished() {
+        for next in simulation.neighbors() {
+            let score = simulation.score_at(next);
+            if score > best {
+                best = score;
+            }
+        }
     }
 
+    simulation.restore(&snapshot);
+    best
+}
+
+/// The incumbent of [`Graph::path_planning_dfs_observed`].
+struct DepthFirstSearch<T> {
+    /// The best complete path so far with whether it is valid, see [`Simulation::is_valid`], and its
+    /// score, a path is complete when the maximum number of timesteps is reached or the agent has
+    /// nowhere to move, or after any step when it may stop early.
+    best: Option<(SimulationSnapshot<T>, (bool, T))>,
+    /// The highest score of a cell which was never visited at the first timestep, at least 0.
+    max_score: T,
+    /// The number of cells of the footprint, each of which collects at most the highest score.
+    footprint: u32,
+    /// The timesteps it takes to pass the waypoints, `None` without waypoints.
+    route: Option<Route>
+}
 
+impl<T: Total> DepthFirstSearch<T> {
+    /// Returns the highest score the remaining steps could collect, as if the agent entered a cell
+    /// on every remaining timestep: an unvisited cell collects its score plus every recovery so far,
+    /// a visited cell, or what is left of it, never collects more.
+    fn upper_bound<S: Score<Total = T>>(&self, simulation: &Simulation<'_, S>) -> T {
+        let remaining = simulation.max_steps().saturating_sub(simulation.timestep()) as u64;
+        let recoveries = simulation.recoveries();
+        let recovery_rate = simulation.options().recovery_rate as u64;
+
+        (recoveries..recoveries + remaining).fold(T::default(), |bound, r| {
+            let recovery = T::from_recovery(recovery_rate * r).unwrap_or(T::MAX);
+            bound.saturating_add(self.max_score.saturating_add(recovery).times(self.footprint))
+        })
+    }
 
+    fn record<S: Score<Total = T>, O: PathfindingObserver<T>>(&mut self, simulation: &Simulation<'_, S>, observer: &mut O) {
+        let key = (simulation.is_valid(), simulation.score());
+        if self.best.as_ref().is_none_or(|&(_, best)| key > best) {
+            self.best = Some((simulation.snapshot(), key));
+            observer.on_event(PathfindingEvent::IncumbentImproved { path: simulation.path(), score: simulation.score() });
+        }
+    }
+
+    fn explore<S: Score<Total = T>, O: PathfindingObserver<T>>(&mut self, simulation: &mut Simulation<'_, S>, observer: &mut O) {
+        let neighbors = if simulation.is_finished() { Vec::new() } else { simulation.neighbors() };
+        let stop_early = simulation.options().penalties.may_stop_early();
+
+        if stop_early {
+            self.record(simulation, observer);
+        }
+
+        // The load can still be unloaded on top of what is collected from now on
+        let reachable = simulation.score().saturating_add(simulation.load().unwrap_or_default()).saturating_add(self.upper_bound(simulation));
+        // A branch which can no longer meet the required windows or pass the waypoints only beats a
+        // best path which doesn't either
+        let hopeless = !simulation.can_meet_windows() || self.route.as_ref().is_some_and(|route| !route.can_reach(simulation));
+        if let Some(&(_, (valid, best))) = self.best.as_ref() && !neighbors.is_empty() && (valid || hopeless) && (reachable <= best || valid && hopeless) {
+            return;
+        }
+
+        let snapshot = simulation.snapshot();
+        let mut expanded = false;
+        for neighbor in neighbors {
+            // A move is only rejected when the total score would overflow, which ends the path like the horizon
+            let Ok(step) = simulation.step(neighbor) else {
+                continue;
+            };
+            observer.on_event(PathfindingEvent::NodeExpanded { node: neighbor, step: step.step });
+            expanded = true;
+
+            self.explore(simulation, observer);
+            simulation.restore(&snapshot);
+        }
+
+        if !expanded && !stop_early {
+            self.record(simulation, observer);
+        }
+    }
 }
 
 #[cfg(test)]
@@ -260,4 +636,198 @@ mod test {
         let graph = Graph::new(3);
         assert_eq!(graph.get_neighbors((2, 1)), vec![(1, 0), (1, 1), (1, 2), (2, 0), (2, 2)]);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_bfs_observer_events() {
+        let graph = Graph::from_bytes(b"0 1 3\n1 4 3\n1 5 7".to_vec());
+        let mut expanded = Vec::new();
+        let mut incumbents = Vec::new();
+        let mut iterations = 0;
+
+        let result = graph.path_planning_bfs_observed((0, 0), &PlanningOptions::new(4, 1), &mut |event: PathfindingEvent<'_>| match event {
+            PathfindingEvent::NodeExpanded { node, .. } => expanded.push(node),
+            PathfindingEvent::IncumbentImproved { score, .. } => incumbents.push(score),
+            PathfindingEvent::IterationFinished { .. } => iterations += 1
+        });
+
+        assert_eq!(expanded, result.path.iter().map(|s| s.node).collect::<Vec<_>>());
+        assert_eq!(incumbents.last(), Some(&result.score()));
+        assert_eq!(iterations, 4);
+    }
+
+    #[test]
+    fn test_bfs_same_seed_same_path() {
+        let graph = Graph::new(5);
+        let options = PlanningOptions { seed: 42, ..PlanningOptions::new(20, 1) };
+        let first = graph.path_planning_bfs_observed((2, 2), &options, &mut NoopObserver);
+        let second = graph.path_planning_bfs_observed((2, 2), &options, &mut NoopObserver);
+
+        assert_eq!(nodes(&first), nodes(&second));
+    }
+
+    #[test]
+    fn test_bfs_seed_breaks_ties() {
+        let graph = Graph::new(5);
+        let paths: Vec<Vec<Position>> = (0..8)
+            .map(|seed| graph.path_planning_bfs_observed((2, 2), &PlanningOptions { seed, ..PlanningOptions::new(4, 1) }, &mut NoopObserver))
+            .map(|result| nodes(&result))
+            .collect();
+
+        assert!(paths.iter().any(|path| path != &paths[0]));
+    }
+
+    #[test]
+    fn test_bfs_pinned_path() {
+        let graph = Graph::from_bytes(b"0 1 3\n1 4 3\n1 5 7".to_vec());
+        let result = graph.path_planning_bfs_observed((0, 0), &PlanningOptions { seed: 7, ..PlanningOptions::new(5, 1) }, &mut NoopObserver);
+
+        assert_eq!(nodes(&result), vec![(0, 0), (1, 1), (2, 2), (2, 1), (1, 2)]);
+        assert_eq!(result.score(), 29);
+    }
+
+    #[test]
+    fn test_bfs_stops_oscillating() {
+        let graph = Graph::from_rows(&[vec![0, 0]]);
+        let options = PlanningOptions::new(6, 5);
+        assert_eq!(nodes(&graph.path_planning_bfs_observed((0, 0), &options, &mut NoopObserver)), [(0, 0), (0, 1)].repeat(3));
+
+        let options = PlanningOptions { revisits: Revisits { limit: Some(1), cooldown: 0 }, ..options };
+        let result = graph.path_planning_bfs_observed((0, 0), &options, &mut NoopObserver);
+        assert_eq!(nodes(&result), [(0, 0), (0, 1)].repeat(2));
+
+        let options = PlanningOptions { revisits: Revisits { limit: None, cooldown: 2 }, ..options };
+        let result = graph.path_planning_bfs_observed((0, 0), &options, &mut NoopObserver);
+        assert_eq!(nodes(&result), [(0, 0), (0, 1)]);
+    }
+
+    #[test]
+    fn test_dfs_beats_bfs() {
+        let graph = Graph::from_file(Path::new("../3.txt"));
+        let options = PlanningOptions::new(5, 1);
+        let dfs = graph.path_planning_dfs_observed((0, 0), &options, &mut NoopObserver);
+
+        assert_eq!(dfs.score(), graph.brute_force((0, 0), &options).score);
+        assert!(dfs.score() >= graph.path_planning_bfs((0, 0), 5, 1).score());
+        assert_eq!(dfs.path.len(), 5);
+    }
+
+    #[test]
+    fn test_planners_stop_before_overflow() {
+        let graph = Graph::from_rows(&[vec![0; 100_000]]);
+        let options = PlanningOptions::new(100_000, u32::MAX);
+        let result = graph.path_planning_bfs_observed((0, 0), &options, &mut NoopObserver);
+
+        assert!(result.path.len() < 100_000);
+        assert_eq!(result.checked_score(), Ok(result.score()));
+        assert_eq!(graph.verify_path(&nodes(&result), &options), Ok(result));
+    }
+
+    #[test]
+    fn test_checked_score() {
+        let step = |step, score| PathfindingStep { node: (0, 0), score, step, energy: None, load: None };
+        let result = PathfindingResult { path: vec![step(1, u64::MAX - 1), step(2, 1), step(3, 1)] };
+
+        assert_eq!(result.checked_score(), Err(VerifyError::ScoreOverflow { step: 3 }));
+        assert_eq!(result.score(), u64::MAX);
+    }
+
+    #[test]
+    fn test_score_beyond_u32() {
+        let graph = Graph::from_rows(&[vec![u32::MAX, u32::MAX]]);
+        let result = graph.path_planning_bfs((0, 0), 2, u32::MAX);
+
+        assert_eq!(result.score(), 2 * u32::MAX as u64 + u32::MAX as u64);
+    }
+
+    /// A corridor where every move from the start costs points, with a reward behind the penalty on the right.
+    fn corridor() -> Graph<i64> {
+        Graph::from_values(&[vec![-4, -2, 0, -1, 10]])
+    }
+
+    #[test]
+    fn test_bfs_accepts_penalties() {
+        let options = PlanningOptions { neighborhood: Neighborhood::VonNeumann, ..PlanningOptions::new(3, 0) };
+        let result = corridor().path_planning_bfs_observed((0, 2), &options, &mut NoopObserver);
+
+        // Greedy takes the smaller penalty and has to move on
+        assert_eq!(nodes(&result), vec![(0, 2), (0, 3), (0, 4)]);
+        assert_eq!(result.score(), 9);
+    }
+
+    #[test]
+    fn test_bfs_avoids_penalties() {
+        let options = PlanningOptions { neighborhood: Neighborhood::VonNeumann, penalties: Penalties::Avoid, ..PlanningOptions::new(3, 0) };
+        let result = corridor().path_planning_bfs_observed((0, 2), &options, &mut NoopObserver);
+
+        assert_eq!(nodes(&result), vec![(0, 2)]);
+        assert_eq!(result.score(), 0);
+    }
+
+    #[test]
+    fn test_bfs_looks_ahead_past_penalties() {
+        let graph = Graph::from_values(&[vec![-1i64, -1, 0, -2, 10]]);
+        let options = PlanningOptions { neighborhood: Neighborhood::VonNeumann, penalties: Penalties::LookAhead, ..PlanningOptions::new(3, 0) };
+        let result = graph.path_planning_bfs_observed((0, 2), &options, &mut NoopObserver);
+
+        // The left penalty is smaller, but only the right one leads to a reward
+        assert_eq!(nodes(&result), vec![(0, 2), (0, 3), (0, 4)]);
+        assert_eq!(result.score(), 8);
+
+        let result = corridor().path_planning_bfs_observed((0, 0), &options, &mut NoopObserver);
+        assert_eq!(nodes(&result), vec![(0, 0)]);
+    }
+
+    #[test]
+    fn test_dfs_may_stop_early() {
+        let graph = Graph::from_values(&[vec![3i64, -5, -5]]);
+        let accept = graph.path_planning_dfs((0, 0), 3, 0);
+        let avoid = graph.path_planning_dfs_observed((0, 0), &PlanningOptions { penalties: Penalties::Avoid, ..PlanningOptions::new(3, 0) }, &mut NoopObserver);
+
+        assert_eq!(accept.score(), -2);
+        assert_eq!(nodes(&avoid), vec![(0, 0)]);
+        assert_eq!(avoid.score(), 3);
+    }
+
+    #[test]
+    fn test_planners_budget_time() {
+        let graph = Graph::from_rows(&[vec![5, 0, 3]]).with_entry_costs(&Graph::from_rows(&[vec![3, 1, 1]])).unwrap();
+        let options = PlanningOptions { neighborhood: Neighborhood::VonNeumann, ..PlanningOptions::new(4, 0) };
+
+        // The left cell is worth more, but the right one is worth more per timestep
+        let bfs = graph.path_planning_bfs_observed((0, 1), &options, &mut NoopObserver);
+        assert_eq!(nodes(&bfs)[1], (0, 2));
+        assert_eq!(bfs.path.last().unwrap().step, 4);
+
+        let dfs = graph.path_planning_dfs_observed((0, 1), &options, &mut NoopObserver);
+        assert_eq!(dfs.path.iter().map(|s| (s.node, s.step)).collect::<Vec<_>>(), vec![((0, 1), 1), ((0, 0), 4)]);
+        assert_eq!(dfs.score(), 5);
+    }
+
+    fn nodes<T>(result: &PathfindingResult<T>) -> Vec<Position> {
+        result.path.iter().map(|s| s.node).collect()
+    }
+
+    #[test]
+    fn test_neighbors_skip_obstacles() {
+        let mut graph = Graph::new(3);
+        graph.add_obstacle((0, 1));
+        graph.add_obstacle((1, 1));
+        assert_eq!(graph.get_neighbors((0, 0)), vec![(1, 0)]);
+    }
+
+    #[test]
+    fn test_von_neumann_neighbors() {
+        let graph = Graph::new(3);
+        assert_eq!(graph.get_neighbors_in((1, 1), Neighborhood::VonNeumann), vec![(0, 1), (1, 0), (1, 2), (2, 1)]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_result_serializes() {
+        let result = PathfindingResult { path: vec![PathfindingStep { node: (0, 1), score: 3, step: 1, energy: None, load: None }] };
+        let json = serde_json::to_string(&result).unwrap();
+
+        assert_eq!(json, r#"{"path":[{"node":[0,1],"score":3,"step":1}]}"#);
+        assert_eq!(serde_json::from_str::<PathfindingResult>(&json).unwrap(), result);
+    }
+}
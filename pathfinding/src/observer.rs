@@ -0,0 +1,36 @@
+use crate::{PathfindingStep, Position};
+
+/// Events emitted by a planner while it is searching for a path, with scores of the total of the
+/// [`Score`](crate::Score) of the graph.
+#[derive(Debug, Clone, Copy)]
+pub enum PathfindingEvent<'a, T = u64> {
+    /// The planner expanded the node at the given position on the given step.
+    NodeExpanded { node: Position, step: u32 },
+    /// The planner found a path which is better than the best path it had so far.
+    IncumbentImproved { path: &'a [PathfindingStep<T>], score: T },
+    /// The planner finished an iteration, the score is the score of the incumbent path.
+    IterationFinished { iteration: u32, score: T }
+}
+
+/// Receives events from a planner while it is running.
+///
+/// Planners are generic over the observer, so the calls to [`NoopObserver`] are inlined away and
+/// cost nothing when nobody is watching.
+pub trait PathfindingObserver<T = u64> {
+    fn on_event(&mut self, event: PathfindingEvent<'_, T>);
+}
+
+/// Observer which ignores every event.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoopObserver;
+
+impl<T> PathfindingObserver<T> for NoopObserver {
+    #[inline(always)]
+    fn on_event(&mut self, _event: PathfindingEvent<'_, T>) {}
+}
+
+impl<T, F: FnMut(PathfindingEvent<'_, T>)> PathfindingObserver<T> for F {
+    fn on_event(&mut self, event: PathfindingEvent<'_, T>) {
+        self(event)
+    }
+}
@@ -0,0 +1,573 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{Position, Total};
+
+/// The cells an agent can move to in a single timestep.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum Neighborhood {
+    /// Horizontal, vertical and diagonal moves (8 neighbors).
+    #[default]
+    Moore,
+    /// Horizontal and vertical moves only (4 neighbors).
+    VonNeumann
+}
+
+impl Neighborhood {
+    /// Returns the offsets of the neighbors, in the order they are visited by the planners.
+    pub fn offsets(&self) -> &'static [(isize, isize)] {
+        match self {
+            Neighborhood::Moore => &[(-1, -1), (-1, 0), (-1, 1), (0, -1), (0, 1), (1, -1), (1, 0), (1, 1)],
+            Neighborhood::VonNeumann => &[(-1, 0), (0, -1), (0, 1), (1, 0)]
+        }
+    }
+
+    /// Returns whether `v` can be reached from `u` in a single move.
+    pub fn is_adjacent(&self, u: Position, v: Position) -> bool {
+        let di = v.0 as isize - u.0 as isize;
+        let dj = v.1 as isize - u.1 as isize;
+        self.offsets().contains(&(di, dj))
+    }
+}
+
+impl fmt::Display for Neighborhood {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Neighborhood::Moore => write!(f, "moore"),
+            Neighborhood::VonNeumann => write!(f, "von-neumann")
+        }
+    }
+}
+
+impl FromStr for Neighborhood {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "moore" | "8" => Ok(Neighborhood::Moore),
+            "von-neumann" | "4" => Ok(Neighborhood::VonNeumann),
+            _ => Err(format!("Unknown neighborhood '{s}', expected 'moore' or 'von-neumann'"))
+        }
+    }
+}
+
+/// What the maximum number of timesteps counts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum Horizon {
+    /// The cells a path enters, including the start, so a path has at most `max_timesteps` steps.
+    #[default]
+    Cells,
+    /// The moves after the start, so a path has at most `max_timesteps + 1` steps.
+    Moves
+}
+
+impl fmt::Display for Horizon {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Horizon::Cells => write!(f, "cells"),
+            Horizon::Moves => write!(f, "moves")
+        }
+    }
+}
+
+impl FromStr for Horizon {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "cells" => Ok(Horizon::Cells),
+            "moves" => Ok(Horizon::Moves),
+            _ => Err(format!("Unknown horizon '{s}', expected 'cells' or 'moves'"))
+        }
+    }
+}
+
+/// Whether the agent collects the score of the cell it starts on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum StartCell {
+    /// The start is collected on the first step and reset like any other cell.
+    #[default]
+    Collected,
+    /// The first step scores 0 and the start keeps its score, to be collected when the agent returns.
+    Skipped
+}
+
+impl fmt::Display for StartCell {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StartCell::Collected => write!(f, "collected"),
+            StartCell::Skipped => write!(f, "skipped")
+        }
+    }
+}
+
+impl FromStr for StartCell {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "collected" => Ok(StartCell::Collected),
+            "skipped" => Ok(StartCell::Skipped),
+            _ => Err(format!("Unknown start cell '{s}', expected 'collected' or 'skipped'"))
+        }
+    }
+}
+
+/// When the cells recover within a step, relative to the agent collecting the cell it enters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum RecoveryOrder {
+    /// The agent collects first and every other cell recovers afterwards, so the first step
+    /// collects the score of the grid and a cell collected on step `s` is worth `rate * (t - s - 1)` on step `t`.
+    #[default]
+    AfterCollection,
+    /// Every cell recovers first and the agent collects afterwards, so the first step already
+    /// collects one recovery and a cell collected on step `s` is worth `rate * (t - s)` on step `t`.
+    BeforeCollection
+}
+
+impl fmt::Display for RecoveryOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecoveryOrder::AfterCollection => write!(f, "after-collection"),
+            RecoveryOrder::BeforeCollection => write!(f, "before-collection")
+        }
+    }
+}
+
+impl FromStr for RecoveryOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "after-collection" | "after" => Ok(RecoveryOrder::AfterCollection),
+            "before-collection" | "before" => Ok(RecoveryOrder::BeforeCollection),
+            _ => Err(format!("Unknown recovery order '{s}', expected 'after-collection' or 'before-collection'"))
+        }
+    }
+}
+
+/// How a path advances through time, applied the same way by the simulation, and so by every
+/// planner and the verifier.
+///
+/// The default counts the cells of a path including the start, collects the start and lets the
+/// cells recover after the agent collected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct TimestepSemantics {
+    pub horizon: Horizon,
+    pub start: StartCell,
+    pub recovery: RecoveryOrder
+}
+
+/// How the planners treat cells which cost points when entered, see [`Score::is_penalty`](crate::Score::is_penalty).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum Penalties {
+    /// A path goes on until the maximum number of timesteps, so the best first search enters the
+    /// best neighbor even when it costs points.
+    #[default]
+    Accept,
+    /// A path may end early rather than lose points, so the best first search stops when every
+    /// neighbor costs points.
+    Avoid,
+    /// Like [`Penalties::Avoid`], but the best first search enters a neighbor which costs points
+    /// when the best cell beyond it makes up for the penalty.
+    LookAhead
+}
+
+impl Penalties {
+    /// Returns whether a path may end before the maximum number of timesteps while the agent could still move.
+    pub fn may_stop_early(&self) -> bool {
+        *self != Penalties::Accept
+    }
+}
+
+impl fmt::Display for Penalties {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Penalties::Accept => write!(f, "accept"),
+            Penalties::Avoid => write!(f, "avoid"),
+            Penalties::LookAhead => write!(f, "look-ahead")
+        }
+    }
+}
+
+impl FromStr for Penalties {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "accept" => Ok(Penalties::Accept),
+            "avoid" => Ok(Penalties::Avoid),
+            "look-ahead" => Ok(Penalties::LookAhead),
+            _ => Err(format!("Unknown penalty policy '{s}', expected 'accept', 'avoid' or 'look-ahead'"))
+        }
+    }
+}
+
+/// The number of timesteps a move takes by its direction, multiplied by the entry cost of the cell
+/// it enters when the graph has an [entry cost layer](crate::Graph::with_entry_costs).
+///
+/// Every move takes one timestep by default. The timesteps can be made finer to tell the
+/// directions apart, like `MoveCosts::euclidean(10)` where a straight move takes 10 timesteps and a
+/// diagonal one 14, with the maximum number of timesteps scaled along.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct MoveCosts {
+    /// The timesteps of a horizontal or vertical move.
+    pub straight: u32,
+    /// The timesteps of a diagonal move.
+    pub diagonal: u32
+}
+
+impl MoveCosts {
+    /// Returns the costs of moves which take as long as the distance they cover, with `resolution`
+    /// timesteps per cell, so a diagonal move takes `resolution * √2` rounded to a whole timestep.
+    pub fn euclidean(resolution: u32) -> Self {
+        MoveCosts { straight: resolution, diagonal: (resolution as f64 * std::f64::consts::SQRT_2).round() as u32 }
+    }
+
+    /// Returns the timesteps of a move from `u` to the adjacent `v`, at least 1.
+    pub fn between(&self, u: Position, v: Position) -> u32 {
+        let cost = if u.0 != v.0 && u.1 != v.1 { self.diagonal } else { self.straight };
+        cost.max(1)
+    }
+}
+
+impl Default for MoveCosts {
+    fn default() -> Self {
+        MoveCosts { straight: 1, diagonal: 1 }
+    }
+}
+
+/// The cells the agent collects when it enters a cell, like the area its sensors cover.
+///
+/// Every cell of the footprint which is not an obstacle is collected and reset, and recovers from
+/// then on like a visited cell. A weighted footprint collects a fraction `1 - d / (radius + 1)` of
+/// a cell at distance `d` from the agent, rounded down for integer scores, and still resets it.
+/// Only the entered cell can cost points, the penalties around it are left alone.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "shape", rename_all = "kebab-case"))]
+pub enum Footprint {
+    /// Only the entered cell.
+    #[default]
+    Cell,
+    /// The cells at most `radius` moves away in any direction, so radius 1 is the 3x3 square.
+    Square {
+        radius: u32,
+        #[cfg_attr(feature = "serde", serde(default))]
+        weighted: bool
+    },
+    /// The cells whose centers are at most `radius` cells away from the center of the entered cell.
+    Disk {
+        radius: f64,
+        #[cfg_attr(feature = "serde", serde(default))]
+        weighted: bool
+    }
+}
+
+impl Footprint {
+    /// Returns the name of the shape, as accepted by [`Footprint::from_str`].
+    pub fn name(&self) -> &'static str {
+        match self {
+            Footprint::Cell => "cell",
+            Footprint::Square { .. } => "square",
+            Footprint::Disk { .. } => "disk"
+        }
+    }
+
+    /// Returns the offsets of the cells of the footprint from the entered cell with the fraction
+    /// of their score which is collected, starting with the entered cell itself.
+    pub fn offsets(&self) -> Vec<((isize, isize), f64)> {
+        let (reach, radius, weighted) = match *self {
+            Footprint::Cell => return vec![((0, 0), 1.0)],
+            Footprint::Square { radius, weighted } => (radius as isize, radius as f64, weighted),
+            Footprint::Disk { radius, weighted } => (radius.max(0.0).floor() as isize, radius, weighted)
+        };
+
+        let mut offsets = vec![((0, 0), 1.0)];
+        for di in -reach..=reach {
+            for dj in -reach..=reach {
+                let distance = match self {
+                    Footprint::Disk { .. } => ((di * di + dj * dj) as f64).sqrt(),
+                    _ => di.abs().max(dj.abs()) as f64
+                };
+
+                if (di, dj) != (0, 0) && distance <= radius {
+                    offsets.push(((di, dj), if weighted { 1.0 - distance / (radius + 1.0) } else { 1.0 }));
+                }
+            }
+        }
+        offsets
+    }
+}
+
+impl fmt::Display for Footprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Footprint {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "cell" => Ok(Footprint::Cell),
+            "square" => Ok(Footprint::Square { radius: 1, weighted: false }),
+            "disk" => Ok(Footprint::Disk { radius: 1.0, weighted: false }),
+            _ => Err(format!("Unknown footprint '{s}', expected 'cell', 'square' or 'disk'"))
+        }
+    }
+}
+
+/// How much of the cells the agent takes when it collects them, the rest stays behind and keeps
+/// recovering for a later visit.
+///
+/// With [`Harvest::Steps`] the agent can wait on a cell, a step to the cell it is on which takes one
+/// timestep, to keep harvesting it.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "mode", rename_all = "kebab-case"))]
+pub enum Harvest {
+    /// The whole score, which resets the cell to 0.
+    #[default]
+    Full,
+    /// A fraction between 0 and 1 of the score, rounded down for integer scores.
+    Fraction { fraction: f64 },
+    /// At most `amount` of the score.
+    Limit { amount: u32 },
+    /// An even share of the score over `steps` consecutive steps on the cell, entering it and
+    /// waiting on it, so the cell is empty after the last one.
+    Steps { steps: u32 }
+}
+
+impl Harvest {
+    /// Returns the name of the mode, as accepted by [`Harvest::from_str`].
+    pub fn name(&self) -> &'static str {
+        match self {
+            Harvest::Full => "full",
+            Harvest::Fraction { .. } => "fraction",
+            Harvest::Limit { .. } => "limit",
+            Harvest::Steps { .. } => "steps"
+        }
+    }
+
+    /// Returns whether the agent may wait on the cell it is on.
+    pub fn allows_waiting(&self) -> bool {
+        matches!(self, Harvest::Steps { steps } if *steps > 1)
+    }
+
+    /// Returns the part of the available score which is taken, when the agent already spent
+    /// `consecutive` steps on the cell right before.
+    pub fn take<T: Total>(&self, available: T, consecutive: u32) -> T {
+        match *self {
+            Harvest::Full => available,
+            Harvest::Fraction { fraction } => available.fraction(fraction.clamp(0.0, 1.0)),
+            Harvest::Limit { amount } => {
+                let amount = T::from_recovery(amount as u64).unwrap_or(T::MAX);
+                if amount < available { amount } else { available }
+            }
+            Harvest::Steps { steps } if consecutive.saturating_add(1) >= steps => available,
+            Harvest::Steps { steps } => available.fraction(1.0 / (steps - consecutive) as f64)
+        }
+    }
+}
+
+impl fmt::Display for Harvest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Harvest {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "full" => Ok(Harvest::Full),
+            "fraction" => Ok(Harvest::Fraction { fraction: 0.5 }),
+            "limit" => Ok(Harvest::Limit { amount: 1 }),
+            "steps" => Ok(Harvest::Steps { steps: 2 }),
+            _ => Err(format!("Unknown harvest '{s}', expected 'full', 'fraction', 'limit' or 'steps'"))
+        }
+    }
+}
+
+/// The battery of the agent, which every move drains and a [charger](crate::Graph::add_charger) refills.
+///
+/// The agent starts with a full battery and a move is only allowed when the battery holds the
+/// energy it uses, so the energy never drops below 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EnergyBudget {
+    /// The energy of a full battery.
+    pub capacity: u32,
+    /// The energy a move uses for every timestep it takes.
+    #[cfg_attr(feature = "serde", serde(default = "EnergyBudget::default_per_timestep"))]
+    pub per_timestep: u32
+}
+
+impl EnergyBudget {
+    /// Creates a battery with the given capacity which a move drains by 1 per timestep.
+    pub fn new(capacity: u32) -> Self {
+        EnergyBudget { capacity, per_timestep: Self::default_per_timestep() }
+    }
+
+    fn default_per_timestep() -> u32 {
+        1
+    }
+}
+
+/// What happens to the score the agent collects beyond the capacity of its [`Hopper`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum Overflow {
+    /// The cells are collected as usual and what doesn't fit is lost.
+    #[default]
+    Lost,
+    /// A cell is only collected when its score fits, otherwise it is left alone.
+    Blocked
+}
+
+impl fmt::Display for Overflow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Overflow::Lost => write!(f, "lost"),
+            Overflow::Blocked => write!(f, "blocked")
+        }
+    }
+}
+
+impl FromStr for Overflow {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "lost" => Ok(Overflow::Lost),
+            "blocked" => Ok(Overflow::Blocked),
+            _ => Err(format!("Unknown overflow '{s}', expected 'lost' or 'blocked'"))
+        }
+    }
+}
+
+/// The load the agent can carry, which a [depot](crate::Graph::add_depot) empties.
+///
+/// The collected score fills the hopper and only counts towards the score of a path once it is
+/// unloaded at a depot, so whatever is still in the hopper at the end is lost. Penalties are not
+/// carried, they cost their score right away.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Hopper {
+    /// The most score the hopper holds.
+    pub capacity: u32,
+    /// What happens to the score beyond the capacity.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub overflow: Overflow
+}
+
+impl Hopper {
+    /// Creates a hopper with the given capacity which loses what doesn't fit.
+    pub fn new(capacity: u32) -> Self {
+        Hopper { capacity, overflow: Overflow::Lost }
+    }
+}
+
+/// How often and how soon the agent may enter a cell again, which keeps it from moving back and
+/// forth between cells that recover quickly when that is undesirable, however well it scores.
+///
+/// Waiting in a cell, see [`Harvest::allows_waiting`], doesn't enter it again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Revisits {
+    /// The most times the agent may enter a cell again after the first time, `None` for no limit.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub limit: Option<u32>,
+    /// The fewest timesteps the agent has to spend elsewhere before it enters a cell again, a
+    /// cooldown of 2 keeps it from moving back and forth between two cells.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub cooldown: u32
+}
+
+/// Options shared by the planners.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PlanningOptions {
+    /// The maximum number of timesteps of a path.
+    pub max_timesteps: u32,
+    /// The score every other cell recovers per timestep.
+    pub recovery_rate: u32,
+    /// Seed for any randomness in a planner, including tie-breaking between equally scored
+    /// neighbors. Planning twice with the same seed gives the same path.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub seed: u64,
+    /// The cells an agent can move to in a single timestep.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub neighborhood: Neighborhood,
+    /// How the timesteps, the start and the recovery are counted.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub semantics: TimestepSemantics,
+    /// How cells which cost points when entered are treated.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub penalties: Penalties,
+    /// The timesteps a move takes, see [`Graph::with_entry_costs`](crate::Graph::with_entry_costs)
+    /// for the time it takes to enter a cell.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub move_costs: MoveCosts,
+    /// The cells the agent collects when it enters a cell.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub footprint: Footprint,
+    /// How much of the cells the agent takes when it collects them.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub harvest: Harvest,
+    /// The battery of the agent, `None` when moves don't use energy.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub energy: Option<EnergyBudget>,
+    /// The load the agent can carry to a depot, `None` when the collected score counts right away.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub hopper: Option<Hopper>,
+    /// The cells a path has to pass through in the given order, which may be passed any number of
+    /// times outside of that order.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Vec::is_empty"))]
+    pub waypoints: Vec<Position>,
+    /// How often and how soon the agent may enter a cell again, without limits by default.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub revisits: Revisits
+}
+
+impl PlanningOptions {
+    /// Creates options for the given horizon and recovery rate, with seed 0, the
+    /// [`Neighborhood::Moore`] neighborhood, the default [`TimestepSemantics`], accepting penalties
+    /// moves of one timestep, collecting the whole score of only the entered cell, and no energy
+    /// budget, hopper, waypoints or revisit limits.
+    pub fn new(max_timesteps: u32, recovery_rate: u32) -> Self {
+        PlanningOptions {
+            max_timesteps,
+            recovery_rate,
+            seed: 0,
+            neighborhood: Neighborhood::Moore,
+            semantics: TimestepSemantics::default(),
+            penalties: Penalties::Accept,
+            move_costs: MoveCosts::default(),
+            footprint: Footprint::Cell,
+            harvest: Harvest::Full,
+            energy: None,
+            hopper: None,
+            waypoints: Vec::new(),
+            revisits: Revisits::default()
+        }
+    }
+}
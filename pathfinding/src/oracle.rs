@@ -0,0 +1,289 @@
+use crate::{Graph, PathfindingResult, PlanningOptions, Position, Score, Simulation};
+
+/// Every path with the highest possible score, found by [`Graph::brute_force`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BruteForceResult<T = u64> {
+    pub score: T,
+    /// Whether the optimal paths are valid, meeting every required [`TimeWindow`](crate::TimeWindow)
+    /// and passing the waypoints in order, which is only `false` when no path can.
+    pub valid: bool,
+    /// The optimal paths in the order of the neighborhood, at least one.
+    pub paths: Vec<PathfindingResult<T>>
+}
+
+impl<S: Score> Graph<S> {
+    /// Enumerates every valid move sequence from the start and returns the highest score with all
+    /// the paths which reach it.
+    ///
+    /// Nothing is pruned, so this is meant as a reference to test the planners against on tiny grids
+    /// and short horizons, the number of sequences grows like `8^max_timesteps`. A path ends when the
+    /// maximum number of timesteps is reached, the agent has nowhere to move or the total score would
+    /// overflow, or after any step when the [`Penalties`](crate::Penalties) of the options let it end
+    /// early. A path which meets every required time window and passes the waypoints of the options
+    /// in order beats any path which doesn't. When the start can't be entered, the only path is the
+    /// empty one.
+    pub fn brute_force(&self, start: Position, options: &PlanningOptions) -> BruteForceResult<S::Total> {
+        let mut simulation = Simulation::new(self, options);
+        let mut optimum = BruteForceResult { score: S::Total::default(), valid: false, paths: Vec::new() };
+
+        if simulation.step(start).is_ok() {
+            enumerate(&mut simulation, &mut optimum);
+        } else {
+            optimum.valid = simulation.is_valid();
+            optimum.paths.push(PathfindingResult::empty());
+        }
+
+        optimum
+    }
+}
+
+fn enumerate<S: Score>(simulation: &mut Simulation<'_, S>, optimum: &mut BruteForceResult<S::Total>) {
+    let neighbors = if simulation.is_finished() { Vec::new() } else { simulation.neighbors() };
+    let stop_early = simulation.options().penalties.may_stop_early();
+
+    if stop_early {
+        record(simulation, optimum);
+    }
+
+    let snapshot = simulation.snapshot();
+    let mut expanded = false;
+    for neighbor in neighbors {
+        // A move is only rejected when the total score would overflow, which ends the path like the horizon
+        if simulation.step(neighbor).is_err() {
+            continue;
+        }
+        expanded = true;
+
+        enumerate(simulation, optimum);
+        simulation.restore(&snapshot);
+    }
+
+    if !expanded && !stop_early {
+        record(simulation, optimum);
+    }
+}
+
+/// Adds the path so far to the optimal paths when it is at least as good.
+fn record<S: Score>(simulation: &Simulation<'_, S>, optimum: &mut BruteForceResult<S::Total>) {
+    // The first path is always recorded, even when its score is below 0
+    let key = (simulation.is_valid(), simulation.score());
+    if optimum.paths.is_empty() || key > (optimum.valid, optimum.score) {
+        (optimum.valid, optimum.score) = key;
+        optimum.paths.clear();
+    }
+    if key == (optimum.valid, optimum.score) {
+        optimum.paths.push(simulation.clone().into_result());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::{Blockage, EnergyBudget, Footprint, Harvest, Hopper, Horizon, MoveCosts, Neighborhood, NoopObserver, Overflow, Penalties, Planner, RecoveryOrder, Revisits, StartCell, TimeWindow, TimestepSemantics, VerifyError};
+
+    #[test]
+    fn test_pinned_optimum() {
+        let graph = Graph::from_bytes(b"0 1 3\n1 4 3\n1 5 7".to_vec());
+        let optimum = graph.brute_force((0, 0), &PlanningOptions::new(3, 1));
+
+        // (0, 0) scores 0, then the center 4 + 1 at step 2 and (2, 2) 7 + 2 at step 3
+        assert_eq!(optimum.score, 14);
+        let paths: Vec<Vec<Position>> = optimum.paths.iter().map(|p| p.path.iter().map(|s| s.node).collect()).collect();
+        assert_eq!(paths, vec![vec![(0, 0), (1, 1), (2, 2)]]);
+    }
+
+    #[test]
+    fn test_counts_all_ties() {
+        let graph = Graph::from_rows(&[vec![1, 1], vec![1, 1]]);
+        let optimum = graph.brute_force((0, 0), &PlanningOptions { neighborhood: Neighborhood::VonNeumann, ..PlanningOptions::new(2, 0) });
+
+        assert_eq!(optimum.score, 2);
+        assert_eq!(optimum.paths.len(), 2);
+    }
+
+    #[test]
+    fn test_invalid_start() {
+        let optimum = Graph::new(2).brute_force((5, 5), &PlanningOptions::new(3, 1));
+        assert_eq!(optimum, BruteForceResult { score: 0, valid: true, paths: vec![PathfindingResult::empty()] });
+    }
+
+    /// A tiny grid with values from the strategy, sometimes with entry costs, chargers, depots, time windows or blockages, and a start
+    /// which is not an obstacle, and options with a short horizon, sometimes with waypoints and revisit limits.
+    fn instance<S: Score>(values: impl Strategy<Value = S> + Clone) -> impl Strategy<Value = (Graph<S>, Position, PlanningOptions)> {
+        (1usize..=3, 1usize..=3)
+            .prop_flat_map(move |(height, width)| {
+                (
+                    prop::collection::vec(prop::collection::vec(values.clone(), width), height),
+                    prop::collection::vec(prop::bool::weighted(0.2), height * width),
+                    (0..height, 0..width),
+                    0u32..=5,
+                    0u32..=3,
+                    any::<bool>(),
+                    any::<u64>(),
+                    semantics(),
+                    prop_oneof![Just(Penalties::Accept), Just(Penalties::Avoid), Just(Penalties::LookAhead)],
+                    prop::option::weighted(0.3, prop::collection::vec(prop::collection::vec(1u32..=3, width), height)),
+                    (1u32..=2, 1u32..=3, footprint(), harvest(), revisits()),
+                    (
+                        prop::option::weighted(0.3, (1u32..=4, prop::collection::vec(prop::bool::weighted(0.2), height * width))),
+                        prop::option::weighted(0.3, (0u32..=10, any::<bool>(), prop::collection::vec(prop::bool::weighted(0.3), height * width))),
+                        prop::collection::vec(prop::option::weighted(0.15, window()), height * width),
+                        prop_oneof![3 => Just(Vec::new()), 1 => prop::collection::vec((0..height, 0..width), 1..=2)],
+                        prop_oneof![3 => Just(Vec::new()), 1 => prop::collection::vec(((0..height, 0..width), (0..height, 0..width), 1u32..=5, 0u32..=2), 1..=2)]
+                    )
+                )
+            })
+            .prop_map(|(rows, obstacles, start, max_timesteps, recovery_rate, moore, seed, semantics, penalties, entry_costs, (straight, diagonal, footprint, harvest, revisits), (energy, hopper, windows, waypoints, blockages))| {
+                let mut graph = Graph::from_values(&rows);
+                if let Some(costs) = entry_costs {
+                    graph = graph.with_entry_costs(&Graph::from_rows(&costs)).unwrap();
+                }
+                let width = rows[0].len();
+                for (k, _) in obstacles.iter().enumerate().filter(|(_, obstacle)| **obstacle) {
+                    if (k / width, k % width) != start {
+                        graph.add_obstacle((k / width, k % width));
+                    }
+                }
+
+                for (k, window) in windows.into_iter().enumerate() {
+                    if let Some(window) = window {
+                        graph.add_window((k / width, k % width), window);
+                    }
+                }
+
+                for (node, corner, from, length) in blockages {
+                    graph.add_blockage(Blockage::region(node, corner, from, from + length));
+                }
+
+                let neighborhood = if moore { Neighborhood::Moore } else { Neighborhood::VonNeumann };
+                let move_costs = MoveCosts { straight, diagonal };
+                let energy = energy.map(|(capacity, chargers)| {
+                    for (k, _) in chargers.iter().enumerate().filter(|(_, charger)| **charger) {
+                        graph.add_charger((k / width, k % width));
+                    }
+                    EnergyBudget::new(capacity)
+                });
+                let hopper = hopper.map(|(capacity, blocked, depots)| {
+                    for (k, _) in depots.iter().enumerate().filter(|(_, depot)| **depot) {
+                        graph.add_depot((k / width, k % width));
+                    }
+                    Hopper { capacity, overflow: if blocked { Overflow::Blocked } else { Overflow::Lost } }
+                });
+                (graph, start, PlanningOptions { seed, neighborhood, semantics, penalties, move_costs, footprint, harvest, energy, hopper, waypoints, revisits, ..PlanningOptions::new(max_timesteps, recovery_rate) })
+            })
+    }
+
+    fn window() -> impl Strategy<Value = TimeWindow> {
+        (1u32..=5, 0u32..=3, prop::bool::weighted(0.5)).prop_map(|(open, length, required)| TimeWindow { open, close: open + length, required })
+    }
+
+    fn footprint() -> impl Strategy<Value = Footprint> {
+        prop_oneof![
+            3 => Just(Footprint::Cell),
+            1 => any::<bool>().prop_map(|weighted| Footprint::Square { radius: 1, weighted }),
+            1 => (0.5..2.0, any::<bool>()).prop_map(|(radius, weighted)| Footprint::Disk { radius, weighted })
+        ]
+    }
+
+    fn harvest() -> impl Strategy<Value = Harvest> {
+        prop_oneof![
+            3 => Just(Harvest::Full),
+            1 => (0.0..=1.0).prop_map(|fraction| Harvest::Fraction { fraction }),
+            1 => (0u32..=5).prop_map(|amount| Harvest::Limit { amount }),
+            1 => (1u32..=3).prop_map(|steps| Harvest::Steps { steps })
+        ]
+    }
+
+    fn revisits() -> impl Strategy<Value = Revisits> {
+        prop_oneof![
+            2 => Just(Revisits::default()),
+            1 => (prop::option::of(0u32..=2), 0u32..=3).prop_map(|(limit, cooldown)| Revisits { limit, cooldown })
+        ]
+    }
+
+    fn semantics() -> impl Strategy<Value = TimestepSemantics> {
+        (any::<bool>(), any::<bool>(), any::<bool>()).prop_map(|(moves, skipped, before)| TimestepSemantics {
+            horizon: if moves { Horizon::Moves } else { Horizon::Cells },
+            start: if skipped { StartCell::Skipped } else { StartCell::Collected },
+            recovery: if before { RecoveryOrder::BeforeCollection } else { RecoveryOrder::AfterCollection }
+        })
+    }
+
+    fn optimal_paths_verify<S: Score>(graph: &Graph<S>, start: Position, options: &PlanningOptions) -> Result<(), TestCaseError> {
+        let optimum = graph.brute_force(start, options);
+
+        for path in &optimum.paths {
+            let positions: Vec<Position> = path.path.iter().map(|s| s.node).collect();
+            match graph.verify_path(&positions, options) {
+                Ok(verified) => {
+                    prop_assert!(optimum.valid);
+                    prop_assert_eq!(verified.score(), optimum.score);
+                    prop_assert_eq!(&verified, path);
+                }
+                // Only when no path meets the required windows and passes the waypoints
+                Err(VerifyError::MissedWindows { .. } | VerifyError::MissedWaypoint { .. }) => prop_assert!(!optimum.valid),
+                Err(error) => return Err(TestCaseError::fail(format!("{error}")))
+            }
+        }
+        Ok(())
+    }
+
+    fn no_planner_beats_oracle<S: Score>(graph: &Graph<S>, start: Position, options: &PlanningOptions) -> Result<(), TestCaseError> {
+        let optimum = graph.brute_force(start, options);
+
+        for planner in [Planner::BestFirstSearch, Planner::DepthFirstSearch] {
+            let result = planner.plan(graph, start, options, &mut NoopObserver);
+            let positions: Vec<Position> = result.path.iter().map(|s| s.node).collect();
+            let valid = graph.verify_path(&positions, options).is_ok();
+
+            prop_assert!(valid <= optimum.valid, "{} found a valid path where the optimum is invalid", planner);
+            if valid == optimum.valid {
+                prop_assert!(result.score() <= optimum.score, "{} scored {} above the optimum {}", planner, result.score(), optimum.score);
+            }
+        }
+        Ok(())
+    }
+
+    fn exact_planners_match_oracle<S: Score>(graph: &Graph<S>, start: Position, options: &PlanningOptions) -> Result<(), TestCaseError> {
+        let optimum = graph.brute_force(start, options);
+        let result = Planner::DepthFirstSearch.plan(graph, start, options, &mut NoopObserver);
+
+        prop_assert_eq!(result.score(), optimum.score);
+        prop_assert!(optimum.paths.contains(&result), "{:?} is not one of the optimal paths", result);
+        Ok(())
+    }
+
+    proptest! {
+        #[test]
+        fn prop_optimal_paths_verify((graph, start, options) in instance(0u32..10)) {
+            optimal_paths_verify(&graph, start, &options)?;
+        }
+
+        #[test]
+        fn prop_no_planner_beats_oracle((graph, start, options) in instance(0u32..10)) {
+            no_planner_beats_oracle(&graph, start, &options)?;
+        }
+
+        #[test]
+        fn prop_exact_planners_match_oracle((graph, start, options) in instance(0u32..10)) {
+            exact_planners_match_oracle(&graph, start, &options)?;
+        }
+
+        #[test]
+        fn prop_penalties_optimal_paths_verify((graph, start, options) in instance(-5i64..10)) {
+            optimal_paths_verify(&graph, start, &options)?;
+        }
+
+        #[test]
+        fn prop_penalties_no_planner_beats_oracle((graph, start, options) in instance(-5i64..10)) {
+            no_planner_beats_oracle(&graph, start, &options)?;
+        }
+
+        #[test]
+        fn prop_penalties_exact_planners_match_oracle((graph, start, options) in instance(-5i64..10)) {
+            exact_planners_match_oracle(&graph, start, &options)?;
+        }
+    }
+}
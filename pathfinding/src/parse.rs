@@ -0,0 +1,340 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::{Graph, Score};
+
+/// The reason a text grid could not be parsed by [`Graph::parse`] or [`Graph::from_esri_ascii`].
+/// Lines and columns start at 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GridParseError {
+    /// The contents are not valid UTF-8.
+    InvalidUtf8,
+    /// The contents don't contain a single row.
+    Empty,
+    /// A cell which is not a valid score, like a negative number for `u32` cells.
+    InvalidScore { line: usize, column: usize, token: String },
+    /// A row with a different number of cells than the first row.
+    RaggedRow { line: usize, expected: usize, found: usize },
+    /// The grid doesn't have the dimensions given in the header, as `(height, width)`.
+    DimensionMismatch { expected: (usize, usize), found: (usize, usize) },
+    /// A header line with an unknown key or a value which is not a number.
+    InvalidHeader { line: usize, token: String },
+    /// The header doesn't give the value with this key.
+    MissingHeader(&'static str)
+}
+
+impl fmt::Display for GridParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GridParseError::InvalidUtf8 => write!(f, "the grid is not valid UTF-8"),
+            GridParseError::Empty => write!(f, "the grid is empty"),
+            GridParseError::InvalidScore { line, column, token } => write!(f, "line {line}, column {column}: '{token}' is not a valid score"),
+            GridParseError::RaggedRow { line, expected, found } => write!(f, "line {line}: expected {expected} cells, found {found}"),
+            GridParseError::DimensionMismatch { expected, found } => write!(f, "the header describes a {}x{} grid, found {}x{}", expected.0, expected.1, found.0, found.1),
+            GridParseError::InvalidHeader { line, token } => write!(f, "line {line}: invalid header '{token}'"),
+            GridParseError::MissingHeader(key) => write!(f, "the header is missing '{key}'")
+        }
+    }
+}
+
+impl Error for GridParseError {}
+
+/// The separator between the cells of a row, detected from the first row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Delimiter {
+    /// Any run of spaces and tabs.
+    Whitespace,
+    Comma,
+    Semicolon
+}
+
+impl Delimiter {
+    /// Prefers semicolons over commas, since spreadsheets which separate cells with semicolons
+    /// write decimal commas.
+    fn detect(line: &str) -> Self {
+        if line.contains(';') {
+            Delimiter::Semicolon
+        } else if line.contains(',') {
+            Delimiter::Comma
+        } else {
+            Delimiter::Whitespace
+        }
+    }
+
+    fn split<'a>(&self, line: &'a str) -> Vec<&'a str> {
+        let separator = match self {
+            Delimiter::Whitespace => return line.split_whitespace().collect(),
+            Delimiter::Comma => ',',
+            Delimiter::Semicolon => ';'
+        };
+
+        let mut cells: Vec<&str> = line.split(separator).map(str::trim).collect();
+
+        // Spreadsheets pad rows with empty trailing columns
+        while cells.last().is_some_and(|c| c.is_empty()) {
+            cells.pop();
+        }
+
+        cells
+    }
+
+    /// Parses a cell, reading a comma as the decimal separator between semicolons.
+    fn parse<S: Score>(&self, token: &str) -> Option<S> {
+        match self {
+            Delimiter::Semicolon => S::parse(&token.replace(',', ".")),
+            _ => S::parse(token)
+        }
+    }
+}
+
+/// Parses a header line like `3x4`, giving the height and width of the grid.
+fn parse_header(line: &str) -> Option<(usize, usize)> {
+    let (height, width) = line.split_once(['x', 'X'])?;
+    Some((height.trim().parse().ok()?, width.trim().parse().ok()?))
+}
+
+impl Graph {
+    /// Parses a grid in the plain-text format, where each line is a row of integer scores, see
+    /// [`Graph::parse_values`].
+    pub fn parse(contents: &str) -> Result<Graph, GridParseError> {
+        Graph::parse_values(contents)
+    }
+
+    /// Parses a grid in the plain-text format from bytes, see [`Graph::parse`].
+    pub fn try_from_bytes(bytes: &[u8]) -> Result<Graph, GridParseError> {
+        let contents = std::str::from_utf8(bytes).map_err(|_| GridParseError::InvalidUtf8)?;
+        Graph::parse(contents)
+    }
+}
+
+impl<S: Score> Graph<S> {
+    /// Parses a grid in the plain-text format, where each line is a row of scores, integers for
+    /// `u32` and `i64` graphs and decimals like `-0.5` for `f64` graphs.
+    ///
+    /// The parser is tolerant of how the grid was produced:
+    ///
+    /// - cells are separated by spaces or tabs (any number of them), by commas or by semicolons,
+    ///   detected from the first row, and between semicolons a comma is a decimal separator
+    /// - empty lines, lines starting with `#`, trailing whitespace, `\r\n` line endings and a
+    ///   leading byte order mark are ignored
+    /// - the first line may be a header with the dimensions as `<height>x<width>`, like `3x3`,
+    ///   which are then checked against the rows
+    ///
+    /// Every row should have the same number of cells, grids don't have to be square.
+    pub fn parse_values(contents: &str) -> Result<Graph<S>, GridParseError> {
+        let contents = contents.strip_prefix('\u{feff}').unwrap_or(contents);
+        let mut lines = contents
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+            .peekable();
+
+        let header = lines.peek().and_then(|(_, line)| parse_header(line));
+        if header.is_some() {
+            lines.next();
+        }
+
+        let mut rows: Vec<Vec<S>> = Vec::new();
+        let mut delimiter = None;
+
+        for (line_number, line) in lines {
+            let delimiter = *delimiter.get_or_insert_with(|| Delimiter::detect(line));
+            let cells = delimiter.split(line);
+
+            if let Some(first) = rows.first() && first.len() != cells.len() {
+                return Err(GridParseError::RaggedRow { line: line_number, expected: first.len(), found: cells.len() });
+            }
+
+            let row = cells
+                .iter()
+                .enumerate()
+                .map(|(j, token)| delimiter.parse(token).ok_or_else(|| GridParseError::InvalidScore { line: line_number, column: j + 1, token: token.to_string() }))
+                .collect::<Result<Vec<S>, _>>()?;
+
+            rows.push(row);
+        }
+
+        if rows.is_empty() {
+            return Err(GridParseError::Empty);
+        }
+
+        let found = (rows.len(), rows[0].len());
+        if let Some(expected) = header && expected != found {
+            return Err(GridParseError::DimensionMismatch { expected, found });
+        }
+
+        Ok(Graph::from_values(&rows))
+    }
+
+    /// Writes the graph in the plain-text format, one line per row with the scores separated by a space.
+    pub fn to_text(&self) -> String {
+        self.to_rows()
+            .iter()
+            .map(|row| row.iter().map(S::to_string).collect::<Vec<_>>().join(" ") + "\n")
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn rows(contents: &str) -> Vec<Vec<u32>> {
+        Graph::parse(contents).unwrap().to_rows()
+    }
+
+    fn expected() -> Vec<Vec<u32>> {
+        vec![vec![0, 1, 3], vec![1, 4, 3], vec![1, 5, 7]]
+    }
+
+    #[test]
+    fn test_single_spaces() {
+        assert_eq!(rows("0 1 3\n1 4 3\n1 5 7"), expected());
+    }
+
+    #[test]
+    fn test_double_spaces() {
+        assert_eq!(rows("0  1   3\n1 4  3\n1 5 7"), expected());
+    }
+
+    #[test]
+    fn test_tabs() {
+        assert_eq!(rows("0\t1\t3\n1\t4\t3\n1\t5\t7"), expected());
+    }
+
+    #[test]
+    fn test_mixed_tabs_and_spaces() {
+        assert_eq!(rows("0 \t1\t 3\n1 4 3\n1 5 7"), expected());
+    }
+
+    #[test]
+    fn test_leading_and_trailing_whitespace() {
+        assert_eq!(rows("  0 1 3  \n1 4 3\t\n 1 5 7 "), expected());
+    }
+
+    #[test]
+    fn test_crlf() {
+        assert_eq!(rows("0 1 3\r\n1 4 3\r\n1 5 7\r\n"), expected());
+    }
+
+    #[test]
+    fn test_blank_trailing_lines() {
+        assert_eq!(rows("0 1 3\n1 4 3\n1 5 7\n\n\n  \n"), expected());
+    }
+
+    #[test]
+    fn test_blank_lines_between_rows() {
+        assert_eq!(rows("0 1 3\n\n1 4 3\n\n1 5 7"), expected());
+    }
+
+    #[test]
+    fn test_comment_lines() {
+        assert_eq!(rows("# survey 2024\n0 1 3\n  # halfway\n1 4 3\n1 5 7\n# end"), expected());
+    }
+
+    #[test]
+    fn test_header() {
+        assert_eq!(rows("3x3\n0 1 3\n1 4 3\n1 5 7"), expected());
+        assert_eq!(rows("# dimensions\n3 X 3\n0 1 3\n1 4 3\n1 5 7"), expected());
+    }
+
+    #[test]
+    fn test_header_mismatch() {
+        assert_eq!(
+            Graph::parse("4x3\n0 1 3\n1 4 3\n1 5 7").unwrap_err(),
+            GridParseError::DimensionMismatch { expected: (4, 3), found: (3, 3) }
+        );
+    }
+
+    #[test]
+    fn test_csv() {
+        assert_eq!(rows("0,1,3\n1,4,3\n1,5,7"), expected());
+        assert_eq!(rows("0, 1, 3\r\n1, 4, 3\r\n1, 5, 7\r\n"), expected());
+    }
+
+    #[test]
+    fn test_csv_trailing_empty_columns() {
+        assert_eq!(rows("0,1,3,,\n1,4,3,,\n1,5,7,,"), expected());
+    }
+
+    #[test]
+    fn test_csv_empty_cell() {
+        assert_eq!(
+            Graph::parse("0,,3\n1,4,3\n1,5,7").unwrap_err(),
+            GridParseError::InvalidScore { line: 1, column: 2, token: String::new() }
+        );
+    }
+
+    #[test]
+    fn test_semicolons() {
+        assert_eq!(rows("0;1;3\n1;4;3\n1;5;7"), expected());
+    }
+
+    #[test]
+    fn test_tsv_from_excel() {
+        assert_eq!(rows("\u{feff}0\t1\t3\r\n1\t4\t3\r\n1\t5\t7\r\n\r\n"), expected());
+    }
+
+    #[test]
+    fn test_rectangular() {
+        let graph = Graph::parse("2x4\n1 2 3 4\n5 6 7 8").unwrap();
+
+        assert_eq!((graph.height(), graph.width()), (2, 4));
+        assert_eq!(*graph.get_score_at((1, 3)), 8);
+        assert_eq!(graph.get_neighbors((1, 3)), vec![(0, 2), (0, 3), (1, 2)]);
+    }
+
+    #[test]
+    fn test_ragged_row() {
+        assert_eq!(
+            Graph::parse("0 1 3\n# comment\n1 4\n1 5 7").unwrap_err(),
+            GridParseError::RaggedRow { line: 3, expected: 3, found: 2 }
+        );
+    }
+
+    #[test]
+    fn test_invalid_score() {
+        assert_eq!(
+            Graph::parse("0 1 3\n1 -4 3\n1 5 7").unwrap_err(),
+            GridParseError::InvalidScore { line: 2, column: 2, token: "-4".to_string() }
+        );
+    }
+
+    #[test]
+    fn test_empty() {
+        assert_eq!(Graph::parse("\n# nothing here\n\n").unwrap_err(), GridParseError::Empty);
+    }
+
+    #[test]
+    fn test_to_text_round_trip() {
+        let graph = Graph::parse("2x4\n1 2 3 4\n5 6 7 80").unwrap();
+
+        assert_eq!(graph.to_text(), "1 2 3 4\n5 6 7 80\n");
+        assert_eq!(rows(&graph.to_text()), graph.to_rows());
+    }
+
+    #[test]
+    fn test_signed_and_float_values() {
+        let signed: Graph<i64> = Graph::parse_values("0, -5, 3\n-1, 4, 3").unwrap();
+        assert_eq!(signed.to_rows(), vec![vec![0, -5, 3], vec![-1, 4, 3]]);
+
+        let float: Graph<f64> = Graph::parse_values("0.5 -1.25\n2 3e1").unwrap();
+        assert_eq!(Graph::<f64>::parse_values("0,5;-1,25\n2;3e1").unwrap().to_rows(), float.to_rows());
+        assert_eq!(float.to_rows(), vec![vec![0.5, -1.25], vec![2.0, 30.0]]);
+        assert_eq!(Graph::<f64>::parse_values(&float.to_text()).unwrap().to_rows(), float.to_rows());
+    }
+
+    #[test]
+    fn test_invalid_float() {
+        assert_eq!(
+            Graph::<f64>::parse_values("0.5 NaN").unwrap_err(),
+            GridParseError::InvalidScore { line: 1, column: 2, token: "NaN".to_string() }
+        );
+    }
+
+    #[test]
+    fn test_invalid_utf8() {
+        assert_eq!(Graph::try_from_bytes(&[0xff, 0xfe, 0x20]).unwrap_err(), GridParseError::InvalidUtf8);
+    }
+}
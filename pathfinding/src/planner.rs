@@ -0,0 +1,49 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{Graph, PathfindingObserver, PathfindingResult, PlanningOptions, Position, Score};
+
+/// The planners which can be selected at runtime, for example from a scenario file or the CLI.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "name", rename_all = "kebab-case"))]
+pub enum Planner {
+    /// The greedy best first search of [`Graph::path_planning_bfs`].
+    #[default]
+    #[cfg_attr(feature = "serde", serde(rename = "best-first"))]
+    BestFirstSearch,
+    /// The exact depth first search of [`Graph::path_planning_dfs`], only usable for short horizons.
+    #[cfg_attr(feature = "serde", serde(rename = "depth-first"))]
+    DepthFirstSearch
+}
+
+impl Planner {
+    /// Plans a path from the start position with this planner.
+    pub fn plan<S: Score, O: PathfindingObserver<S::Total>>(&self, graph: &Graph<S>, start: Position, options: &PlanningOptions, observer: &mut O) -> PathfindingResult<S::Total> {
+        match self {
+            Planner::BestFirstSearch => graph.path_planning_bfs_observed(start, options, observer),
+            Planner::DepthFirstSearch => graph.path_planning_dfs_observed(start, options, observer)
+        }
+    }
+}
+
+impl fmt::Display for Planner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Planner::BestFirstSearch => write!(f, "best-first"),
+            Planner::DepthFirstSearch => write!(f, "depth-first")
+        }
+    }
+}
+
+impl FromStr for Planner {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "best-first" | "bfs" => Ok(Planner::BestFirstSearch),
+            "depth-first" | "dfs" => Ok(Planner::DepthFirstSearch),
+            _ => Err(format!("Unknown planner '{s}', expected 'best-first' or 'depth-first'"))
+        }
+    }
+}
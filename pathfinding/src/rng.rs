@@ -0,0 +1,27 @@
+/// Small deterministic random number generator (SplitMix64).
+///
+/// Used instead of an external crate so a seed produces the same sequence on every platform and
+/// every version of the library, which keeps recorded runs reproducible.
+#[derive(Debug, Clone)]
+pub(crate) struct SplitMix64 {
+    state: u64
+}
+
+impl SplitMix64 {
+    pub(crate) fn new(seed: u64) -> Self {
+        SplitMix64 { state: seed }
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a value between 0 (inclusive) and 1 (exclusive).
+    pub(crate) fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
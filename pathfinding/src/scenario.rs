@@ -0,0 +1,431 @@
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Blockage, Graph, GridFileError, PathfindingObserver, PathfindingResult, Planner, PlanningOptions, Position, TimeWindow};
+
+/// A complete, reproducible run: the grid, the start positions, the planner and its options.
+///
+/// Scenarios are stored as TOML or JSON, for example
+///
+/// ```toml
+/// starts = [[0, 0], [2, 1]]
+/// max_timesteps = 10
+/// recovery_rate = 1
+/// neighborhood = "moore"
+/// seed = 42
+///
+/// [grid]
+/// path = "3.txt"
+///
+/// [planner]
+/// name = "best-first"
+/// ```
+///
+/// Instead of a `path`, relative to the scenario file, the grid can be given inline as `rows`. An
+/// `[entry_costs]` table, given the same way, adds the time it takes to enter each cell, see
+/// [`Graph::with_entry_costs`], and a `[move_costs]` table the timesteps of a straight and a diagonal move.
+/// The `obstacles` can't be entered, like the NODATA cells of an ESRI grid. The `chargers` refill the battery of an `[energy]` budget and the `depots` empty a `[hopper]`.
+/// Every entry of `[[windows]]` gives a `node` a [`TimeWindow`], and the `waypoints` have to be
+/// passed in order. Every entry of `[[blockages]]` closes a `node`, or the rectangle up to its
+/// `corner`, `from` one timestep `until` another, see [`Blockage`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Scenario {
+    pub grid: GridSource,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub entry_costs: Option<GridSource>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub obstacles: Vec<Position>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub chargers: Vec<Position>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub depots: Vec<Position>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub windows: Vec<NodeWindow>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub blockages: Vec<Blockage>,
+    pub starts: Vec<Position>,
+    #[serde(default)]
+    pub planner: Planner,
+    #[serde(flatten)]
+    pub options: PlanningOptions
+}
+
+/// The time window of a node of a [`Scenario`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NodeWindow {
+    pub node: Position,
+    #[serde(flatten)]
+    pub window: TimeWindow
+}
+
+/// Where the grid of a [`Scenario`] comes from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum GridSource {
+    /// A grid file in the text or binary format, relative paths are resolved against the directory
+    /// of the scenario file.
+    Path(PathBuf),
+    /// The scores of the grid, one list per row.
+    Rows(Vec<Vec<u32>>)
+}
+
+/// The formats a [`Scenario`] can be stored in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScenarioFormat {
+    Toml,
+    Json
+}
+
+impl ScenarioFormat {
+    /// Picks the format from the extension of the path, TOML unless the extension is `json`.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(e) if e.eq_ignore_ascii_case("json") => ScenarioFormat::Json,
+            _ => ScenarioFormat::Toml
+        }
+    }
+}
+
+/// The reason a [`Scenario`] could not be loaded or saved.
+#[derive(Debug)]
+pub enum ScenarioError {
+    Io(PathBuf, std::io::Error),
+    Toml(String),
+    Json(serde_json::Error),
+    /// The grid file can't be parsed or the rows of the inline grid don't have the same length.
+    Grid(String)
+}
+
+impl fmt::Display for ScenarioError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScenarioError::Io(path, error) => write!(f, "{}: {error}", path.display()),
+            ScenarioError::Toml(error) => write!(f, "invalid TOML scenario: {error}"),
+            ScenarioError::Json(error) => write!(f, "invalid JSON scenario: {error}"),
+            ScenarioError::Grid(error) => write!(f, "invalid grid: {error}")
+        }
+    }
+}
+
+impl Error for ScenarioError {}
+
+impl Scenario {
+    /// Creates a scenario which stores the graph inline, with its entry costs, obstacles, chargers,
+    /// depots, time windows and blockages.
+    pub fn inline(graph: &Graph, starts: Vec<Position>, planner: Planner, options: PlanningOptions) -> Self {
+        let entry_costs = graph.has_entry_costs().then(|| GridSource::Rows(graph.entry_cost_rows()));
+        Scenario { grid: GridSource::Rows(graph.to_rows()), entry_costs, obstacles: graph.obstacles(), chargers: graph.chargers(), depots: graph.depots(), windows: graph.windows().into_iter().map(|(node, window)| NodeWindow { node, window }).collect(), blockages: graph.blockages().to_vec(), starts, planner, options }
+    }
+
+    /// Parses a scenario in the given format.
+    pub fn parse(contents: &str, format: ScenarioFormat) -> Result<Self, ScenarioError> {
+        match format {
+            ScenarioFormat::Toml => toml::from_str(contents).map_err(|e| ScenarioError::Toml(e.to_string())),
+            ScenarioFormat::Json => serde_json::from_str(contents).map_err(ScenarioError::Json)
+        }
+    }
+
+    /// Serializes the scenario in the given format.
+    pub fn to_string(&self, format: ScenarioFormat) -> Result<String, ScenarioError> {
+        match format {
+            ScenarioFormat::Toml => toml::to_string(self).map_err(|e| ScenarioError::Toml(e.to_string())),
+            ScenarioFormat::Json => serde_json::to_string_pretty(self).map_err(ScenarioError::Json)
+        }
+    }
+
+    /// Loads a scenario from a file, picking the format from its extension.
+    pub fn from_file(path: &Path) -> Result<Self, ScenarioError> {
+        let contents = fs::read_to_string(path).map_err(|e| ScenarioError::Io(path.to_path_buf(), e))?;
+        Scenario::parse(&contents, ScenarioFormat::from_path(path))
+    }
+
+    /// Saves the scenario to a file, picking the format from its extension.
+    pub fn save(&self, path: &Path) -> Result<(), ScenarioError> {
+        let contents = self.to_string(ScenarioFormat::from_path(path))?;
+        fs::write(path, contents).map_err(|e| ScenarioError::Io(path.to_path_buf(), e))
+    }
+
+    /// Builds the graph of the scenario, resolving relative grid paths against `base_dir`.
+    pub fn graph(&self, base_dir: &Path) -> Result<Graph, ScenarioError> {
+        let mut graph = self.grid.load(base_dir)?;
+
+        if let Some(costs) = &self.entry_costs {
+            graph = graph.with_entry_costs(&costs.load(base_dir)?).map_err(|e| ScenarioError::Grid(e.to_string()))?;
+        }
+
+        for &obstacle in &self.obstacles {
+            if !graph.contains(obstacle) {
+                return Err(ScenarioError::Grid(format!("the obstacle {obstacle:?} is outside of the grid")));
+            }
+            graph.add_obstacle(obstacle);
+        }
+
+        for &charger in &self.chargers {
+            if !graph.contains(charger) {
+                return Err(ScenarioError::Grid(format!("the charger {charger:?} is outside of the grid")));
+            }
+            graph.add_charger(charger);
+        }
+
+        for &depot in &self.depots {
+            if !graph.contains(depot) {
+                return Err(ScenarioError::Grid(format!("the depot {depot:?} is outside of the grid")));
+            }
+            graph.add_depot(depot);
+        }
+
+        for &NodeWindow { node, window } in &self.windows {
+            if !graph.contains(node) {
+                return Err(ScenarioError::Grid(format!("the time window of {node:?} is outside of the grid")));
+            }
+            graph.add_window(node, window);
+        }
+
+        for &blockage in &self.blockages {
+            if !graph.contains(blockage.node) || blockage.corner.is_some_and(|corner| !graph.contains(corner)) {
+                return Err(ScenarioError::Grid(format!("the blockage of {:?} is outside of the grid", blockage.node)));
+            }
+            if blockage.from > blockage.until {
+                return Err(ScenarioError::Grid(format!("the blockage of {:?} ends on timestep {} before it starts on {}", blockage.node, blockage.until, blockage.from)));
+            }
+            graph.add_blockage(blockage);
+        }
+
+        if let Some(waypoint) = self.options.waypoints.iter().find(|&&u| !graph.contains(u)) {
+            return Err(ScenarioError::Grid(format!("the waypoint {waypoint:?} is outside of the grid")));
+        }
+
+        if let Some(start) = self.starts.iter().find(|&&u| !graph.contains(u)) {
+            return Err(ScenarioError::Grid(format!("the start {start:?} is outside of the grid")));
+        }
+
+        Ok(graph)
+    }
+
+    /// Plans a path from every start of the scenario.
+    pub fn run<O: PathfindingObserver>(&self, graph: &Graph, observer: &mut O) -> Vec<PathfindingResult> {
+        self.starts
+            .iter()
+            .map(|&start| self.planner.plan(graph, start, &self.options, observer))
+            .collect()
+    }
+}
+
+impl GridSource {
+    fn load(&self, base_dir: &Path) -> Result<Graph, ScenarioError> {
+        match self {
+            GridSource::Path(path) => {
+                Graph::open(&base_dir.join(path)).map_err(|e| match e {
+                    GridFileError::Io(path, error) => ScenarioError::Io(path, error),
+                    error => ScenarioError::Grid(error.to_string())
+                })
+            }
+            GridSource::Rows(rows) => {
+                let width = rows.first().map_or(0, |row| row.len());
+                if let Some(row) = rows.iter().find(|row| row.len() != width) {
+                    return Err(ScenarioError::Grid(format!("expected {width} columns per row, got {}", row.len())));
+                }
+                Ok(Graph::from_rows(rows))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Horizon, Neighborhood, NoopObserver, RecoveryOrder, TimestepSemantics};
+
+    const SCENARIO: &str = r#"
+starts = [[0, 0], [2, 1]]
+max_timesteps = 5
+recovery_rate = 2
+neighborhood = "von-neumann"
+seed = 7
+
+[grid]
+rows = [[0, 1, 3], [1, 4, 3], [1, 5, 7]]
+
+[planner]
+name = "best-first"
+"#;
+
+    #[test]
+    fn test_parse_toml() {
+        let scenario = Scenario::parse(SCENARIO, ScenarioFormat::Toml).unwrap();
+
+        assert_eq!(scenario.starts, vec![(0, 0), (2, 1)]);
+        assert_eq!(scenario.planner, Planner::BestFirstSearch);
+        assert_eq!(scenario.options, PlanningOptions { seed: 7, neighborhood: Neighborhood::VonNeumann, ..PlanningOptions::new(5, 2) });
+    }
+
+    #[test]
+    fn test_defaults() {
+        let scenario = Scenario::parse(r#"{"grid": {"rows": [[1]]}, "starts": [[0, 0]], "max_timesteps": 3, "recovery_rate": 1}"#, ScenarioFormat::Json).unwrap();
+
+        assert_eq!(scenario.planner, Planner::BestFirstSearch);
+        assert_eq!(scenario.options, PlanningOptions::new(3, 1));
+    }
+
+    #[test]
+    fn test_parse_semantics() {
+        let contents = format!("{SCENARIO}\n[semantics]\nhorizon = \"moves\"\nrecovery = \"before-collection\"\n");
+        let scenario = Scenario::parse(&contents, ScenarioFormat::Toml).unwrap();
+
+        let semantics = TimestepSemantics { horizon: Horizon::Moves, recovery: RecoveryOrder::BeforeCollection, ..Default::default() };
+        assert_eq!(scenario.options.semantics, semantics);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let scenario = Scenario::parse(SCENARIO, ScenarioFormat::Toml).unwrap();
+
+        for format in [ScenarioFormat::Toml, ScenarioFormat::Json] {
+            let contents = scenario.to_string(format).unwrap();
+            assert_eq!(Scenario::parse(&contents, format).unwrap(), scenario);
+        }
+    }
+
+    #[test]
+    fn test_run_is_reproducible() {
+        let scenario = Scenario::parse(SCENARIO, ScenarioFormat::Toml).unwrap();
+        let graph = scenario.graph(Path::new(".")).unwrap();
+        let results = scenario.run(&graph, &mut NoopObserver);
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results, scenario.run(&graph, &mut NoopObserver));
+        assert_eq!(results[0], graph.path_planning_bfs_observed((0, 0), &scenario.options, &mut NoopObserver));
+    }
+
+    #[test]
+    fn test_entry_costs() {
+        let contents = format!("{SCENARIO}\n[entry_costs]\nrows = [[1, 1, 1], [1, 3, 1], [1, 1, 1]]\n\n[move_costs]\ndiagonal = 2\n");
+        let scenario = Scenario::parse(&contents, ScenarioFormat::Toml).unwrap();
+        let graph = scenario.graph(Path::new(".")).unwrap();
+
+        assert_eq!(graph.entry_cost((1, 1)), 3);
+        assert_eq!(scenario.options.move_costs, crate::MoveCosts { straight: 1, diagonal: 2 });
+        assert_eq!(Scenario::inline(&graph, vec![(0, 0)], Planner::BestFirstSearch, scenario.options.clone()).entry_costs, scenario.entry_costs);
+    }
+
+    #[test]
+    fn test_energy() {
+        let contents = format!("chargers = [[1, 1]]\n{SCENARIO}\n[energy]\ncapacity = 4\n");
+        let scenario = Scenario::parse(&contents, ScenarioFormat::Toml).unwrap();
+        let graph = scenario.graph(Path::new(".")).unwrap();
+
+        assert!(graph.is_charger((1, 1)));
+        assert_eq!(scenario.options.energy, Some(crate::EnergyBudget::new(4)));
+
+        let saved = Scenario::inline(&graph, scenario.starts.clone(), scenario.planner.clone(), scenario.options.clone());
+        assert_eq!(Scenario::parse(&saved.to_string(ScenarioFormat::Toml).unwrap(), ScenarioFormat::Toml).unwrap(), saved);
+    }
+
+    #[test]
+    fn test_parse_footprint() {
+        let contents = format!("{SCENARIO}\n[footprint]\nshape = \"disk\"\nradius = 2.5\nweighted = true\n");
+        let scenario = Scenario::parse(&contents, ScenarioFormat::Toml).unwrap();
+
+        assert_eq!(scenario.options.footprint, crate::Footprint::Disk { radius: 2.5, weighted: true });
+        assert_eq!(Scenario::parse(&scenario.to_string(ScenarioFormat::Json).unwrap(), ScenarioFormat::Json).unwrap(), scenario);
+    }
+
+    #[test]
+    fn test_hopper() {
+        let contents = format!("depots = [[0, 0]]\n{SCENARIO}\n[hopper]\ncapacity = 6\noverflow = \"blocked\"\n");
+        let scenario = Scenario::parse(&contents, ScenarioFormat::Toml).unwrap();
+
+        assert!(scenario.graph(Path::new(".")).unwrap().is_depot((0, 0)));
+        assert_eq!(scenario.options.hopper, Some(crate::Hopper { capacity: 6, overflow: crate::Overflow::Blocked }));
+        assert!(Scenario::parse(&contents.replace("[[0, 0]]", "[[3, 0]]"), ScenarioFormat::Toml).unwrap().graph(Path::new(".")).is_err());
+    }
+
+    #[test]
+    fn test_windows() {
+        let contents = format!("{SCENARIO}\n[[windows]]\nnode = [2, 2]\nopen = 2\nclose = 4\nrequired = true\n");
+        let scenario = Scenario::parse(&contents, ScenarioFormat::Toml).unwrap();
+        let graph = scenario.graph(Path::new(".")).unwrap();
+
+        assert_eq!(graph.window((2, 2)), Some(TimeWindow { open: 2, close: 4, required: true }));
+        assert_eq!(Scenario::inline(&graph, scenario.starts.clone(), scenario.planner.clone(), scenario.options.clone()).windows, scenario.windows);
+        assert_eq!(Scenario::parse(&scenario.to_string(ScenarioFormat::Toml).unwrap(), ScenarioFormat::Toml).unwrap(), scenario);
+    }
+
+    #[test]
+    fn test_waypoints() {
+        let contents = format!("waypoints = [[2, 2], [0, 1]]\n{SCENARIO}");
+        let scenario = Scenario::parse(&contents, ScenarioFormat::Toml).unwrap();
+
+        assert_eq!(scenario.options.waypoints, vec![(2, 2), (0, 1)]);
+        assert_eq!(Scenario::parse(&scenario.to_string(ScenarioFormat::Toml).unwrap(), ScenarioFormat::Toml).unwrap(), scenario);
+        assert!(Scenario::parse(&contents.replace("[0, 1]", "[0, 3]"), ScenarioFormat::Toml).unwrap().graph(Path::new(".")).is_err());
+    }
+
+    #[test]
+    fn test_blockages() {
+        let contents = format!("{SCENARIO}\n[[blockages]]\nnode = [0, 1]\ncorner = [2, 1]\nfrom = 2\nuntil = 3\n");
+        let scenario = Scenario::parse(&contents, ScenarioFormat::Toml).unwrap();
+        let graph = scenario.graph(Path::new(".")).unwrap();
+
+        assert_eq!(graph.blockages(), [Blockage::region((0, 1), (2, 1), 2, 3)]);
+        assert_eq!(Scenario::inline(&graph, scenario.starts.clone(), scenario.planner.clone(), scenario.options.clone()).blockages, scenario.blockages);
+        assert_eq!(Scenario::parse(&scenario.to_string(ScenarioFormat::Toml).unwrap(), ScenarioFormat::Toml).unwrap(), scenario);
+    }
+
+    #[test]
+    fn test_obstacles() {
+        let (mut graph, _) = Graph::from_esri_ascii("ncols 2\nnrows 2\nxllcorner 0\nyllcorner 0\ncellsize 1\nNODATA_value -1\n1 -1\n3 4").unwrap();
+        graph.add_obstacle((1, 0));
+        let scenario = Scenario::inline(&graph, vec![(1, 1)], Planner::default(), PlanningOptions::new(3, 1));
+        assert_eq!(scenario.obstacles, vec![(0, 1), (1, 0)]);
+
+        for format in [ScenarioFormat::Toml, ScenarioFormat::Json] {
+            let loaded = Scenario::parse(&scenario.to_string(format).unwrap(), format).unwrap();
+            assert_eq!(loaded.graph(Path::new(".")).unwrap().obstacles(), graph.obstacles());
+        }
+
+        let outside = Scenario { obstacles: vec![(2, 0)], ..scenario };
+        assert!(matches!(outside.graph(Path::new(".")), Err(ScenarioError::Grid(error)) if error.contains("(2, 0)")));
+    }
+
+    #[test]
+    fn test_rejects_reversed_blockage() {
+        let contents = format!("{SCENARIO}\n[[blockages]]\nnode = [0, 1]\nfrom = 3\nuntil = 2\n");
+        let scenario = Scenario::parse(&contents, ScenarioFormat::Toml).unwrap();
+        assert!(matches!(scenario.graph(Path::new(".")), Err(ScenarioError::Grid(error)) if error.contains("ends on timestep 2")));
+    }
+
+    #[test]
+    fn test_parse_harvest() {
+        let contents = format!("{SCENARIO}\n[harvest]\nmode = \"steps\"\nsteps = 3\n");
+        let scenario = Scenario::parse(&contents, ScenarioFormat::Toml).unwrap();
+
+        assert_eq!(scenario.options.harvest, crate::Harvest::Steps { steps: 3 });
+        assert_eq!(Scenario::parse(&scenario.to_string(ScenarioFormat::Json).unwrap(), ScenarioFormat::Json).unwrap(), scenario);
+    }
+
+    #[test]
+    fn test_parse_revisits() {
+        let contents = format!("{SCENARIO}\n[revisits]\nlimit = 2\ncooldown = 3\n");
+        let scenario = Scenario::parse(&contents, ScenarioFormat::Toml).unwrap();
+
+        assert_eq!(scenario.options.revisits, crate::Revisits { limit: Some(2), cooldown: 3 });
+        assert_eq!(Scenario::parse(&scenario.to_string(ScenarioFormat::Toml).unwrap(), ScenarioFormat::Toml).unwrap(), scenario);
+    }
+
+    #[test]
+    fn test_rejects_ragged_rows() {
+        let scenario = Scenario::parse(r#"{"grid": {"rows": [[1, 2], [3]]}, "starts": [], "max_timesteps": 3, "recovery_rate": 1}"#, ScenarioFormat::Json).unwrap();
+        assert!(matches!(scenario.graph(Path::new(".")), Err(ScenarioError::Grid(_))));
+    }
+
+    #[test]
+    fn test_rejects_start_outside_grid() {
+        let scenario = Scenario::parse(r#"{"grid": {"rows": [[1, 2], [3, 4]]}, "starts": [[9, 9]], "max_timesteps": 3, "recovery_rate": 1}"#, ScenarioFormat::Json).unwrap();
+        assert!(matches!(scenario.graph(Path::new(".")), Err(ScenarioError::Grid(error)) if error.contains("(9, 9)")));
+    }
+}
@@ -0,0 +1,276 @@
+use std::fmt::{Debug, Display};
+
+/// The value of a cell of a [`Graph`](crate::Graph).
+///
+/// Implemented for `u32`, the default, for `i64` and for `f64`. A cell with a value below 0 is a
+/// penalty, which costs its value every time the agent enters it and never recovers.
+pub trait Score: Copy + PartialOrd + Default + Debug + Display + Send + Sync + 'static {
+    /// The type the scores of the steps and of a path are added up in.
+    type Total: Total;
+
+    /// Parses the value of a cell, `None` when the token is not a valid value.
+    fn parse(token: &str) -> Option<Self>;
+
+    /// Converts a measured value, like a raster value, rounding and clamping it when the type can't hold it.
+    fn from_f64(value: f64) -> Self;
+
+    /// Returns the value as a total, which always fits.
+    fn to_total(self) -> Self::Total;
+
+    /// Returns whether entering the cell costs points.
+    fn is_penalty(self) -> bool {
+        self < Self::default()
+    }
+}
+
+/// The score of a step or of a path, see [`Score::Total`].
+pub trait Total: Copy + PartialOrd + Default + Debug + Display + Send + Sync + 'static {
+    /// The highest total, or infinity.
+    const MAX: Self;
+
+    /// Adds the totals, `None` when the sum doesn't fit.
+    fn checked_add(self, other: Self) -> Option<Self>;
+
+    /// Adds the totals, saturating at the bounds of the type.
+    fn saturating_add(self, other: Self) -> Self;
+
+    /// Subtracts the totals, saturating at the bounds of the type.
+    fn saturating_sub(self, other: Self) -> Self;
+
+    /// Converts the recovery of a cell, the recovery rate times the number of recoveries, `None`
+    /// when it doesn't fit.
+    fn from_recovery(recovery: u64) -> Option<Self>;
+
+    /// Divides the total by a number of timesteps, rounding toward 0 for integers.
+    fn per(self, timesteps: u32) -> Self;
+
+    /// Multiplies the total by a count, saturating at the bounds of the type.
+    fn times(self, count: u32) -> Self;
+
+    /// Takes a fraction between 0 and 1 of the total, rounding down for integers.
+    ///
+    /// Integers above 2^53 don't fit in an `f64`, so their fraction is taken with 128-bit integers.
+    fn fraction(self, fraction: f64) -> Self;
+}
+
+impl Score for u32 {
+    type Total = u64;
+
+    fn parse(token: &str) -> Option<Self> {
+        token.parse().ok()
+    }
+
+    fn from_f64(value: f64) -> Self {
+        value.round().clamp(0.0, u32::MAX as f64) as u32
+    }
+
+    fn to_total(self) -> u64 {
+        self as u64
+    }
+}
+
+impl Score for i64 {
+    type Total = i64;
+
+    fn parse(token: &str) -> Option<Self> {
+        token.parse().ok()
+    }
+
+    fn from_f64(value: f64) -> Self {
+        // Casting saturates at the bounds of i64
+        value.round() as i64
+    }
+
+    fn to_total(self) -> i64 {
+        self
+    }
+}
+
+impl Score for f64 {
+    type Total = f64;
+
+    fn parse(token: &str) -> Option<Self> {
+        token.parse().ok().filter(|value: &f64| value.is_finite())
+    }
+
+    fn from_f64(value: f64) -> Self {
+        value
+    }
+
+    fn to_total(self) -> f64 {
+        self
+    }
+}
+
+/// Every integer up to this one is exact as an `f64`.
+const EXACT_FLOAT: u64 = 1 << 53;
+
+/// Returns the fraction between 0 and 1 of the value rounded down, and whether nothing was rounded
+/// off, computed exactly from the mantissa and the exponent of the fraction.
+fn exact_fraction(value: u64, fraction: f64) -> (u64, bool) {
+    if fraction.is_nan() || fraction <= 0.0 {
+        return (0, value == 0);
+    }
+
+    let bits = fraction.to_bits();
+    let (mantissa, exponent) = match ((bits >> 52) & 0x7ff) as u32 {
+        0 => (bits & ((1 << 52) - 1), 1074),
+        biased => (bits & ((1 << 52) - 1) | (1 << 52), 1075 - biased)
+    };
+
+    // A fraction below 1 has an exponent of at least 53
+    let product = value as u128 * mantissa as u128;
+    match exponent {
+        0..128 => ((product >> exponent) as u64, product & ((1 << exponent) - 1) == 0),
+        _ => (0, product == 0)
+    }
+}
+
+impl Total for u64 {
+    const MAX: Self = u64::MAX;
+
+    fn checked_add(self, other: Self) -> Option<Self> {
+        u64::checked_add(self, other)
+    }
+
+    fn saturating_add(self, other: Self) -> Self {
+        u64::saturating_add(self, other)
+    }
+
+    fn saturating_sub(self, other: Self) -> Self {
+        u64::saturating_sub(self, other)
+    }
+
+    fn from_recovery(recovery: u64) -> Option<Self> {
+        Some(recovery)
+    }
+
+    fn per(self, timesteps: u32) -> Self {
+        self / timesteps as u64
+    }
+
+    fn times(self, count: u32) -> Self {
+        self.saturating_mul(count as u64)
+    }
+
+    fn fraction(self, fraction: f64) -> Self {
+        if self <= EXACT_FLOAT || fraction >= 1.0 {
+            return (self as f64 * fraction).floor() as u64;
+        }
+        exact_fraction(self, fraction).0
+    }
+}
+
+impl Total for i64 {
+    const MAX: Self = i64::MAX;
+
+    fn checked_add(self, other: Self) -> Option<Self> {
+        i64::checked_add(self, other)
+    }
+
+    fn saturating_add(self, other: Self) -> Self {
+        i64::saturating_add(self, other)
+    }
+
+    fn saturating_sub(self, other: Self) -> Self {
+        i64::saturating_sub(self, other)
+    }
+
+    fn from_recovery(recovery: u64) -> Option<Self> {
+        i64::try_from(recovery).ok()
+    }
+
+    fn per(self, timesteps: u32) -> Self {
+        self / timesteps as i64
+    }
+
+    fn times(self, count: u32) -> Self {
+        self.saturating_mul(count as i64)
+    }
+
+    fn fraction(self, fraction: f64) -> Self {
+        if self.unsigned_abs() <= EXACT_FLOAT || fraction >= 1.0 {
+            return (self as f64 * fraction).floor() as i64;
+        }
+
+        let (taken, exact) = exact_fraction(self.unsigned_abs(), fraction);
+        match self {
+            0.. => taken as i64,
+            _ => -(taken as i64) - i64::from(!exact)
+        }
+    }
+}
+
+impl Total for f64 {
+    const MAX: Self = f64::INFINITY;
+
+    fn checked_add(self, other: Self) -> Option<Self> {
+        Some(self + other).filter(|sum| sum.is_finite())
+    }
+
+    fn saturating_add(self, other: Self) -> Self {
+        self + other
+    }
+
+    fn saturating_sub(self, other: Self) -> Self {
+        self - other
+    }
+
+    fn from_recovery(recovery: u64) -> Option<Self> {
+        Some(recovery as f64)
+    }
+
+    fn per(self, timesteps: u32) -> Self {
+        self / timesteps as f64
+    }
+
+    fn times(self, count: u32) -> Self {
+        self * count as f64
+    }
+
+    fn fraction(self, fraction: f64) -> Self {
+        self * fraction
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(<u32 as Score>::parse("-3"), None);
+        assert_eq!(<i64 as Score>::parse("-3"), Some(-3));
+        assert_eq!(<f64 as Score>::parse("-0.25"), Some(-0.25));
+        assert_eq!(<f64 as Score>::parse("NaN"), None);
+        assert_eq!(<f64 as Score>::parse("inf"), None);
+    }
+
+    #[test]
+    fn test_from_f64() {
+        assert_eq!(u32::from_f64(-2.6), 0);
+        assert_eq!(u32::from_f64(2.5), 3);
+        assert_eq!(i64::from_f64(-2.6), -3);
+        assert_eq!(f64::from_f64(-2.6), -2.6);
+    }
+
+    #[test]
+    fn test_fraction_of_large_totals() {
+        // Below 2^53 the float product is exact, above it the integer one has to be
+        assert_eq!(Total::fraction(10u64, 0.5), 5);
+        assert_eq!(Total::fraction(EXACT_FLOAT + 3, 0.5), (EXACT_FLOAT >> 1) + 1);
+        assert_eq!(Total::fraction(u64::MAX, 0.5), u64::MAX / 2);
+        assert_eq!(Total::fraction(u64::MAX, 0.0), 0);
+        assert_eq!(Total::fraction(i64::MAX, 0.5), i64::MAX / 2);
+        assert_eq!(Total::fraction(i64::MIN + 1, 0.5), i64::MIN / 2);
+        assert_eq!(Total::fraction(i64::MIN, 0.25), i64::MIN / 4);
+        assert_eq!(Total::fraction(u64::MAX, f64::MIN_POSITIVE), 0);
+    }
+
+    #[test]
+    fn test_overflow() {
+        assert_eq!(Total::checked_add(i64::MAX, 1), None);
+        assert_eq!(Total::checked_add(f64::MAX, f64::MAX), None);
+        assert_eq!(<i64 as Total>::from_recovery(u64::MAX), None);
+    }
+}
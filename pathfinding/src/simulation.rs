@@ -0,0 +1,739 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{Graph, Horizon, Overflow, PathfindingResult, PathfindingStep, PlanningOptions, Position, RecoveryOrder, Score, StartCell, TimeWindow, Total, VerifyError};
+
+/// Applies the recovery and reset rules of the planners one move at a time.
+///
+/// The scores are not stored per cell, instead a cell remembers the timestep it was last visited
+/// and its score is derived from that, so a step costs the same on a 3x3 grid as on a 10000x10000 grid.
+///
+/// The [`TimestepSemantics`](crate::TimestepSemantics) of the options decide how many steps a path
+/// has, whether the start is collected and whether the cells recover before or after the agent collects.
+///
+/// A penalty cell, with a score below 0, costs its score every time the agent enters it. It is
+/// never reset and doesn't recover.
+///
+/// The agent enters the start on timestep 1 and every move takes the [`MoveCosts`](crate::MoveCosts)
+/// of its direction times the [entry cost](Graph::entry_cost) of the cell it enters, one timestep
+/// by default. The cells recover on every timestep which passes, and a path ends once the time is up.
+///
+/// The [`Harvest`](crate::Harvest) of the options decides how much of a cell the agent takes, what
+/// it leaves behind recovers on top of the rest.
+///
+/// With an [`EnergyBudget`](crate::EnergyBudget), every move drains the battery by the energy it
+/// uses, a charger refills it and a move the battery can't pay for is rejected.
+///
+/// With a [`Hopper`](crate::Hopper), the collected score is loaded into the hopper and a step only
+/// scores what it unloads at a depot.
+///
+/// A cell with a [`TimeWindow`] is only collected while its window is open.
+///
+/// The waypoints of the options are passed in order, entering the next waypoint passes it.
+///
+/// The [`Revisits`](crate::Revisits) of the options limit how often and how soon a cell is entered
+/// again, and a cell can't be entered on a timestep its [`Blockage`](crate::Blockage) closes it.
+#[derive(Debug, Clone)]
+pub struct Simulation<'a, S: Score = u32> {
+    graph: &'a Graph<S>,
+    options: &'a PlanningOptions,
+    /// The offsets of the footprint of the options, see [`Footprint::offsets`](crate::Footprint::offsets).
+    footprint: Vec<((isize, isize), f64)>,
+    state: SimulationSnapshot<S::Total>
+}
+
+/// What entering a cell collects, the score of the step and the load of the hopper after it.
+struct Collection<T> {
+    score: T,
+    load: Option<T>
+}
+
+/// The state of a [`Simulation`] at a timestep, which can be restored later on.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SimulationSnapshot<T = u64> {
+    /// The timestep every visited cell was last collected on, with the score left in it.
+    last_visited: HashMap<Position, (u32, T)>,
+    path: Vec<PathfindingStep<T>>,
+    score: T,
+    energy: Option<u32>,
+    load: Option<T>,
+    /// The cells whose time window was met.
+    met: HashSet<Position>,
+    /// The number of waypoints passed in order.
+    waypoints: usize,
+    /// The times every entered cell was entered again, with the last timestep the agent was in it.
+    entries: HashMap<Position, (u32, u32)>
+}
+
+impl<'a, S: Score> Simulation<'a, S> {
+    /// Creates a simulation on the graph before the agent has entered its first cell.
+    pub fn new(graph: &'a Graph<S>, options: &'a PlanningOptions) -> Self {
+        let state = SimulationSnapshot {
+            last_visited: HashMap::new(),
+            path: Vec::new(),
+            score: S::Total::default(),
+            energy: options.energy.map(|budget| budget.capacity),
+            load: options.hopper.map(|_| S::Total::default()),
+            met: HashSet::new(),
+            waypoints: 0,
+            entries: HashMap::new()
+        };
+        Simulation { graph, options, footprint: options.footprint.offsets(), state }
+    }
+
+    /// Returns the graph the simulation runs on.
+    pub fn graph(&self) -> &'a Graph<S> {
+        self.graph
+    }
+
+    /// Returns the options the simulation runs with.
+    pub fn options(&self) -> &'a PlanningOptions {
+        self.options
+    }
+
+    /// Returns the timestep of the last step, 0 before the first step. This is the number of steps
+    /// taken so far when every move takes one timestep.
+    pub fn timestep(&self) -> u32 {
+        self.state.path.last().map_or(0, |s| s.step)
+    }
+
+    /// Returns the position of the agent, or `None` before the first step.
+    pub fn position(&self) -> Option<Position> {
+        self.state.path.last().map(|s| s.node)
+    }
+
+    /// Returns the cumulative score of the steps taken so far.
+    pub fn score(&self) -> S::Total {
+        self.state.score
+    }
+
+    /// Returns the timestep a path ends on, which is the number of cells it enters including the
+    /// start when every move takes one timestep.
+    pub fn max_steps(&self) -> u32 {
+        match self.options.semantics.horizon {
+            Horizon::Cells => self.options.max_timesteps,
+            Horizon::Moves => self.options.max_timesteps.saturating_add(1)
+        }
+    }
+
+    /// Returns whether the maximum number of timesteps has been reached.
+    pub fn is_finished(&self) -> bool {
+        self.timestep() >= self.max_steps()
+    }
+
+    /// Returns how many times the cells have recovered when the agent collects on the next timestep.
+    pub fn recoveries(&self) -> u64 {
+        self.recoveries_at(self.timestep() + 1)
+    }
+
+    /// Returns how many times the cells have recovered when the agent collects on the given timestep.
+    fn recoveries_at(&self, timestep: u32) -> u64 {
+        match self.options.semantics.recovery {
+            RecoveryOrder::AfterCollection => timestep as u64 - 1,
+            RecoveryOrder::BeforeCollection => timestep as u64
+        }
+    }
+
+    /// Returns the number of timesteps a move from the current position into the cell takes, 1 for
+    /// the start and for waiting on the current cell.
+    pub fn duration(&self, u: Position) -> u32 {
+        match self.position() {
+            Some(from) if from == u => 1,
+            Some(from) => self.graph.entry_cost(u).saturating_mul(self.options.move_costs.between(from, u)),
+            None => 1
+        }
+    }
+
+    /// Returns the timestep the agent would enter the cell on with the next step.
+    pub fn arrival(&self, u: Position) -> u32 {
+        self.timestep().saturating_add(self.duration(u))
+    }
+
+    /// Returns the energy left in the battery, `None` without an energy budget.
+    pub fn energy(&self) -> Option<u32> {
+        self.state.energy
+    }
+
+    /// Returns the energy a move from the current position into the cell uses, 0 for the start.
+    pub fn energy_cost(&self, u: Position) -> u32 {
+        match (self.options.energy, self.position()) {
+            (Some(budget), Some(_)) => budget.per_timestep.saturating_mul(self.duration(u)),
+            _ => 0
+        }
+    }
+
+    /// Returns the energy which would be left after entering the cell on the next step, `None`
+    /// without an energy budget.
+    pub fn energy_after(&self, u: Position) -> Option<u32> {
+        let budget = self.options.energy?;
+        if self.graph.is_charger(u) {
+            Some(budget.capacity)
+        } else {
+            Some(self.state.energy?.saturating_sub(self.energy_cost(u)))
+        }
+    }
+
+    /// Returns whether the battery holds the energy a move into the cell uses.
+    fn can_afford(&self, u: Position) -> bool {
+        self.state.energy.is_none_or(|energy| self.energy_cost(u) <= energy)
+    }
+
+    /// Returns the load in the hopper, `None` without a hopper.
+    pub fn load(&self) -> Option<S::Total> {
+        self.state.load
+    }
+
+    /// Returns the load which would be in the hopper after entering the cell on the next step, `None`
+    /// without a hopper.
+    pub fn load_after(&self, u: Position) -> Option<S::Total> {
+        self.checked_collect_at(u, |_, _| ()).map_or(self.state.load, |collection| collection.load)
+    }
+
+    /// Returns the steps taken so far.
+    pub fn path(&self) -> &[PathfindingStep<S::Total>] {
+        &self.state.path
+    }
+
+    /// Returns the score the agent would collect by entering the cell on the next step, the total of
+    /// the cells of the [`Footprint`](crate::Footprint) around it, or with a hopper the load it
+    /// unloads when the cell is a depot.
+    ///
+    /// A visited cell starts from 0 and every cell except the one the agent is on recovers by the
+    /// recovery rate on every timestep, including the timesteps the move into the cell takes. When
+    /// the start is skipped, the first step collects nothing.
+    ///
+    /// The score of a single `u32` cell always fits in a `u64`, even with the highest recovery
+    /// rate, other scores saturate at the bounds of their total.
+    pub fn score_at(&self, u: Position) -> S::Total {
+        self.checked_collect_at(u, |_, _| ()).map_or(S::Total::MAX, |collection| collection.score)
+    }
+
+    /// Returns the score the cell alone holds when the agent would enter it on the next step, which
+    /// is [`Simulation::score_at`] when only the entered cell is collected and it is taken in full.
+    pub fn cell_score_at(&self, u: Position) -> S::Total {
+        if self.state.path.is_empty() && self.options.semantics.start == StartCell::Skipped {
+            return S::Total::default();
+        }
+        self.checked_value_at(u, self.arrival(u)).unwrap_or(S::Total::MAX)
+    }
+
+    /// Returns what the agent would collect by entering the cell on the next step, `None` when it
+    /// doesn't fit, and passes every collected cell with the score left in it to `collected`.
+    fn checked_collect_at(&self, u: Position, mut collected: impl FnMut(Position, S::Total)) -> Option<Collection<S::Total>> {
+        let mut collection = Collection { score: S::Total::default(), load: self.state.load };
+        if self.state.path.is_empty() && self.options.semantics.start == StartCell::Skipped {
+            return Some(collection);
+        }
+
+        let (arrival, consecutive) = (self.arrival(u), self.consecutive(u));
+        let capacity = self.options.hopper.map(|hopper| (S::Total::from_recovery(hopper.capacity as u64).unwrap_or(S::Total::MAX), hopper.overflow));
+
+        for (v, fraction) in self.footprint_around(u) {
+            let value = self.checked_value_at(v, arrival)?;
+
+            if self.graph.window(v).is_some_and(|window| !window.contains(arrival)) {
+                continue;
+            }
+
+            // Only the entered cell can cost points, and the cost isn't carried
+            if self.graph.get_score_at(v).is_penalty() {
+                if v == u {
+                    collection.score = collection.score.checked_add(value)?;
+                }
+                collected(v, S::Total::default());
+                continue;
+            }
+
+            let taken = self.options.harvest.take(value, consecutive);
+            let gain = if fraction < 1.0 { taken.fraction(fraction) } else { taken };
+            match (capacity, collection.load) {
+                (Some((capacity, overflow)), Some(load)) => {
+                    let load = load.checked_add(gain)?;
+                    if load <= capacity {
+                        collection.load = Some(load);
+                    } else if overflow == Overflow::Lost {
+                        collection.load = Some(capacity);
+                    } else {
+                        // The cell keeps its score, but its window was still met
+                        collected(v, value);
+                        continue;
+                    }
+                }
+                _ => collection.score = collection.score.checked_add(gain)?
+            }
+            collected(v, value.saturating_sub(taken));
+        }
+
+        if let Some(load) = collection.load && self.graph.is_depot(u) {
+            collection.score = collection.score.checked_add(load)?;
+            collection.load = Some(S::Total::default());
+        }
+
+        Some(collection)
+    }
+
+    /// Returns the number of steps in a row the agent spent on the cell, 0 unless it waits on it.
+    fn consecutive(&self, u: Position) -> u32 {
+        self.state.path.iter().rev().take_while(|s| s.node == u).count() as u32
+    }
+
+    /// Returns the score of a single cell when it is collected on the given timestep, `None` when it doesn't fit.
+    fn checked_value_at(&self, u: Position, timestep: u32) -> Option<S::Total> {
+        let value = *self.graph.get_score_at(u);
+        if value.is_penalty() {
+            return Some(value.to_total());
+        }
+
+        let (recoveries, recovery_rate) = (self.recoveries_at(timestep), self.options.recovery_rate as u64);
+        match self.state.last_visited.get(&u) {
+            // The recoveries up to and including the step the cell was collected on don't count
+            Some(&(visited, left)) => left.checked_add(S::Total::from_recovery(recovery_rate * (recoveries - visited as u64))?),
+            None => value.to_total().checked_add(S::Total::from_recovery(recovery_rate * recoveries)?)
+        }
+    }
+
+    /// Returns the cells of the footprint around the cell which are inside the graph and not an
+    /// obstacle, with the fraction of their score which is collected, starting with the cell itself.
+    pub fn footprint_around(&self, u: Position) -> impl Iterator<Item = (Position, f64)> + '_ {
+        self.footprint.iter().filter_map(move |&((di, dj), fraction)| {
+            let v = (u.0.checked_add_signed(di)?, u.1.checked_add_signed(dj)?);
+            (self.graph.contains(v) && (v == u || !self.graph.is_obstacle(v))).then_some((v, fraction))
+        })
+    }
+
+    /// Returns the number of timesteps since the cell was last visited, or `None` if it wasn't visited yet.
+    pub fn time_since_visit(&self, u: Position) -> Option<u32> {
+        self.state.last_visited.get(&u).map(|&(visited, _)| self.timestep() - visited)
+    }
+
+    /// Returns whether the time window of the cell was met.
+    pub fn has_met(&self, u: Position) -> bool {
+        self.state.met.contains(&u)
+    }
+
+    /// Returns the time windows which were not met so far, row by row.
+    pub fn missed_windows(&self) -> Vec<(Position, TimeWindow)> {
+        self.graph.windows().into_iter().filter(|&(u, _)| !self.has_met(u)).collect()
+    }
+
+    /// Returns whether every required time window was met, which a valid path has to do by its end.
+    pub fn meets_windows(&self) -> bool {
+        self.graph.windows.iter().all(|(&u, window)| !window.required || self.has_met(u))
+    }
+
+    /// Returns whether every required time window which was not met yet is still open after the
+    /// current timestep.
+    pub(crate) fn can_meet_windows(&self) -> bool {
+        self.graph.windows.iter().all(|(&u, window)| !window.required || self.has_met(u) || window.close > self.timestep())
+    }
+
+    /// Returns the number of waypoints of the options passed so far, the first ones in their order.
+    pub fn reached_waypoints(&self) -> usize {
+        self.state.waypoints
+    }
+
+    /// Returns whether every waypoint was passed in order, which a valid path has to do by its end.
+    pub fn meets_waypoints(&self) -> bool {
+        self.state.waypoints == self.options.waypoints.len()
+    }
+
+    /// Returns whether the path so far would be valid if it ended here, meeting every required
+    /// time window and passing every waypoint.
+    pub fn is_valid(&self) -> bool {
+        self.meets_windows() && self.meets_waypoints()
+    }
+
+    /// Returns the scores of all cells at the current timestep, indexed the same way as the graph.
+    pub fn score_grid(&self) -> Vec<Vec<S::Total>> {
+        let (height, width) = (self.graph.height(), self.graph.width());
+        (0..height).map(|i| (0..width).map(|j| self.cell_score_at((i, j))).collect()).collect()
+    }
+
+    /// Returns the number of timesteps since each cell was last visited, indexed the same way as the graph.
+    pub fn time_since_visit_grid(&self) -> Vec<Vec<Option<u32>>> {
+        let (height, width) = (self.graph.height(), self.graph.width());
+        (0..height).map(|i| (0..width).map(|j| self.time_since_visit((i, j))).collect()).collect()
+    }
+
+    /// Returns the error entering the cell on the next step breaks the [`Revisits`](crate::Revisits)
+    /// of the options with, `None` when it may enter the cell. Waiting is always allowed.
+    fn revisit_error(&self, u: Position, step: u32) -> Option<VerifyError> {
+        if self.position() == Some(u) {
+            return None;
+        }
+
+        let &(revisits, left) = self.state.entries.get(&u)?;
+        let limits = self.options.revisits;
+        if limits.limit.is_some_and(|limit| revisits >= limit) {
+            Some(VerifyError::RevisitLimit { step, node: u })
+        } else if self.arrival(u) <= left.saturating_add(limits.cooldown) {
+            Some(VerifyError::Cooldown { step, node: u })
+        } else {
+            None
+        }
+    }
+
+    /// Returns the cells the agent can move to on the next step, leaving out the cells it can't
+    /// enter before the time is up, while they are blocked, with the energy left or under the
+    /// revisit limits. When the [`Harvest`](crate::Harvest)
+    /// allows waiting, the current cell comes last.
+    pub fn neighbors(&self) -> Vec<Position> {
+        match self.position() {
+            Some(u) => self.graph
+                .get_neighbors_in(u, self.options.neighborhood)
+                .into_iter()
+                .chain(self.options.harvest.allows_waiting().then_some(u))
+                .filter(|&v| self.arrival(v) <= self.max_steps() && !self.graph.is_blocked(v, self.arrival(v)) && self.can_afford(v) && self.revisit_error(v, 0).is_none())
+                .collect(),
+            None => Vec::new()
+        }
+    }
+
+    /// Checks whether the agent is allowed to enter the cell on the next step.
+    pub fn check_move(&self, u: Position) -> Result<(), VerifyError> {
+        let step = self.state.path.len() as u32 + 1;
+
+        if self.timestep() >= self.max_steps() {
+            return Err(VerifyError::ExceedsHorizon { step, max_timesteps: self.options.max_timesteps });
+        }
+
+        if !self.graph.contains(u) {
+            return Err(VerifyError::OutOfBounds { step, node: u });
+        }
+
+        if self.graph.is_obstacle(u) {
+            return Err(VerifyError::Obstacle { step, node: u });
+        }
+
+        let waits = self.position() == Some(u) && self.options.harvest.allows_waiting();
+        if let Some(from) = self.position() && !waits && !self.options.neighborhood.is_adjacent(from, u) {
+            return Err(VerifyError::NotAdjacent { step, from, to: u });
+        }
+
+        if self.arrival(u) > self.max_steps() {
+            return Err(VerifyError::ExceedsHorizon { step, max_timesteps: self.options.max_timesteps });
+        }
+
+        if self.graph.is_blocked(u, self.arrival(u)) {
+            return Err(VerifyError::Blocked { step, node: u });
+        }
+
+        if !self.can_afford(u) {
+            return Err(VerifyError::OutOfEnergy { step, node: u });
+        }
+
+        if let Some(error) = self.revisit_error(u, step) {
+            return Err(error);
+        }
+
+        Ok(())
+    }
+
+    /// Moves the agent into the cell, collecting the score of its footprint and leaving what the
+    /// [`Harvest`](crate::Harvest) doesn't take, which is 0 by default, or what doesn't fit in a
+    /// hopper which blocks its overflow.
+    ///
+    /// The move is rejected with [`VerifyError::ScoreOverflow`] when the cumulative score would no
+    /// longer fit in the total, which takes a very high recovery rate over a very long horizon.
+    pub fn step(&mut self, u: Position) -> Result<PathfindingStep<S::Total>, VerifyError> {
+        self.check_move(u)?;
+
+        let overflow = VerifyError::ScoreOverflow { step: self.state.path.len() as u32 + 1 };
+        let mut cells = Vec::new();
+        let collection = self.checked_collect_at(u, |v, left| cells.push((v, left))).ok_or(overflow.clone())?;
+        let step = PathfindingStep { node: u, score: collection.score, step: self.arrival(u), energy: self.energy_after(u), load: collection.load };
+        let waits = self.position() == Some(u);
+
+        self.state.score = self.state.score.checked_add(step.score).ok_or(overflow)?;
+        self.state.energy = step.energy;
+        self.state.load = step.load;
+        self.state.path.push(step);
+        for (v, left) in cells {
+            self.state.last_visited.insert(v, (step.step, left));
+            if self.graph.window(v).is_some_and(|window| window.contains(step.step)) {
+                self.state.met.insert(v);
+            }
+        }
+        match self.state.entries.get_mut(&u) {
+            Some((_, left)) if waits => *left = step.step,
+            Some((revisits, left)) => (*revisits, *left) = (*revisits + 1, step.step),
+            None => { self.state.entries.insert(u, (0, step.step)); }
+        }
+        if self.options.waypoints.get(self.state.waypoints) == Some(&u) {
+            self.state.waypoints += 1;
+        }
+
+        Ok(step)
+    }
+
+    /// Captures the current state so it can be restored with [`Simulation::restore`].
+    pub fn snapshot(&self) -> SimulationSnapshot<S::Total> {
+        self.state.clone()
+    }
+
+    /// Restores a state captured with [`Simulation::snapshot`].
+    pub fn restore(&mut self, snapshot: &SimulationSnapshot<S::Total>) {
+        self.state.clone_from(snapshot);
+    }
+
+    /// Returns the steps taken so far as a result.
+    pub fn into_result(self) -> PathfindingResult<S::Total> {
+        PathfindingResult { path: self.state.path }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::TimestepSemantics;
+
+    fn graph() -> Graph {
+        Graph::from_bytes(b"0 1 3\n1 4 3\n1 5 7".to_vec())
+    }
+
+    #[test]
+    fn test_score_grid_after_steps() {
+        let graph = graph();
+        let options = PlanningOptions::new(10, 1);
+        let mut simulation = Simulation::new(&graph, &options);
+
+        simulation.step((1, 1)).unwrap();
+        simulation.step((2, 2)).unwrap();
+
+        assert_eq!(simulation.score_grid(), vec![vec![2, 3, 5], vec![3, 1, 5], vec![3, 7, 0]]);
+        assert_eq!(simulation.time_since_visit((1, 1)), Some(1));
+        assert_eq!(simulation.time_since_visit((0, 0)), None);
+        assert_eq!(simulation.score(), 4 + 8);
+    }
+
+    #[test]
+    fn test_matches_reset_and_recover() {
+        let graph = graph();
+        let options = PlanningOptions::new(10, 3);
+        let mut simulation = Simulation::new(&graph, &options);
+        let mut expected = graph.clone();
+
+        for node in [(0, 0), (0, 1), (1, 1), (0, 1), (0, 0)] {
+            assert_eq!(simulation.step(node).unwrap().score, *expected.get_score_at(node) as u64);
+            expected = expected.reset_score(node).recover_for(3, node);
+        }
+
+        for i in 0..3 {
+            for j in 0..3 {
+                assert_eq!(simulation.score_at((i, j)), *expected.get_score_at((i, j)) as u64);
+            }
+        }
+    }
+
+    #[test]
+    fn test_snapshot_restore() {
+        let graph = graph();
+        let options = PlanningOptions::new(10, 1);
+        let mut simulation = Simulation::new(&graph, &options);
+
+        simulation.step((0, 0)).unwrap();
+        let snapshot = simulation.snapshot();
+        let grid = simulation.score_grid();
+
+        simulation.step((1, 1)).unwrap();
+        simulation.step((2, 2)).unwrap();
+        simulation.restore(&snapshot);
+
+        assert_eq!(simulation.timestep(), 1);
+        assert_eq!(simulation.position(), Some((0, 0)));
+        assert_eq!(simulation.score_grid(), grid);
+    }
+
+    #[test]
+    fn test_revisit_limit() {
+        let graph = graph();
+        let options = PlanningOptions { revisits: crate::Revisits { limit: Some(1), cooldown: 0 }, ..PlanningOptions::new(10, 1) };
+        let mut simulation = Simulation::new(&graph, &options);
+
+        for u in [(0, 0), (1, 1), (0, 0), (1, 1)] {
+            simulation.step(u).unwrap();
+        }
+
+        assert!(!simulation.neighbors().contains(&(0, 0)));
+        assert_eq!(simulation.step((0, 0)), Err(VerifyError::RevisitLimit { step: 5, node: (0, 0) }));
+    }
+
+    #[test]
+    fn test_cooldown() {
+        let graph = graph();
+        let options = PlanningOptions { revisits: crate::Revisits { limit: None, cooldown: 2 }, ..PlanningOptions::new(10, 1) };
+        let mut simulation = Simulation::new(&graph, &options);
+
+        simulation.step((0, 0)).unwrap();
+        simulation.step((1, 1)).unwrap();
+        assert_eq!(simulation.step((0, 0)), Err(VerifyError::Cooldown { step: 3, node: (0, 0) }));
+
+        // Two timesteps elsewhere are enough
+        simulation.step((0, 1)).unwrap();
+        assert!(simulation.step((0, 0)).is_ok());
+    }
+
+    #[test]
+    fn test_rejects_illegal_move() {
+        let graph = graph();
+        let options = PlanningOptions::new(10, 1);
+        let mut simulation = Simulation::new(&graph, &options);
+
+        simulation.step((0, 0)).unwrap();
+
+        assert_eq!(simulation.step((2, 0)), Err(VerifyError::NotAdjacent { step: 2, from: (0, 0), to: (2, 0) }));
+        assert_eq!(simulation.timestep(), 1);
+    }
+
+    fn scores(options: &PlanningOptions, path: &[Position]) -> Vec<u64> {
+        let graph = graph();
+        let mut simulation = Simulation::new(&graph, options);
+        path.iter().map(|&node| simulation.step(node).unwrap().score).collect()
+    }
+
+    #[test]
+    fn test_default_semantics() {
+        let options = PlanningOptions::new(4, 2);
+
+        // The start collects its score, and a cell collected on step 2 is worth 2 * (4 - 2 - 1) on step 4
+        assert_eq!(scores(&options, &[(0, 1), (1, 1), (0, 1), (1, 1)]), vec![1, 4 + 2, 2, 2]);
+    }
+
+    #[test]
+    fn test_horizon_counts_moves() {
+        let graph = graph();
+        let options = PlanningOptions { semantics: TimestepSemantics { horizon: Horizon::Moves, ..Default::default() }, ..PlanningOptions::new(2, 1) };
+        let mut simulation = Simulation::new(&graph, &options);
+
+        for node in [(0, 0), (1, 1), (2, 2)] {
+            assert!(!simulation.is_finished());
+            simulation.step(node).unwrap();
+        }
+
+        assert!(simulation.is_finished());
+        assert_eq!(simulation.step((2, 1)), Err(VerifyError::ExceedsHorizon { step: 4, max_timesteps: 2 }));
+    }
+
+    #[test]
+    fn test_start_skipped() {
+        let options = PlanningOptions { semantics: TimestepSemantics { start: StartCell::Skipped, ..Default::default() }, ..PlanningOptions::new(3, 1) };
+
+        // The start isn't collected, so it is still worth its score plus the recovery when returning
+        assert_eq!(scores(&options, &[(0, 1), (1, 1), (0, 1)]), vec![0, 4 + 1, 1 + 2]);
+    }
+
+    #[test]
+    fn test_penalty_costs_every_visit() {
+        let graph = Graph::from_values(&[vec![2i64, -3, 1]]);
+        let options = PlanningOptions::new(5, 2);
+        let mut simulation = Simulation::new(&graph, &options);
+
+        let scores: Vec<i64> = [(0, 0), (0, 1), (0, 2), (0, 1), (0, 0)].into_iter().map(|u| simulation.step(u).unwrap().score).collect();
+
+        // The penalty doesn't recover while the other cells do
+        assert_eq!(scores, vec![2, -3, 1 + 4, -3, 2 * 3]);
+        assert_eq!(simulation.score(), 2 - 3 + 5 - 3 + 6);
+    }
+
+    #[test]
+    fn test_fractional_scores() {
+        let graph = Graph::from_values(&[vec![0.5, -0.25]]);
+        let options = PlanningOptions::new(3, 1);
+        let mut simulation = Simulation::new(&graph, &options);
+
+        for u in [(0, 0), (0, 1), (0, 0)] {
+            simulation.step(u).unwrap();
+        }
+        assert_eq!(simulation.score(), 0.5 - 0.25 + 1.0);
+    }
+
+    #[test]
+    fn test_recovery_before_collection() {
+        let options = PlanningOptions { semantics: TimestepSemantics { recovery: RecoveryOrder::BeforeCollection, ..Default::default() }, ..PlanningOptions::new(4, 2) };
+
+        // Every step collects one more recovery than with the default semantics
+        assert_eq!(scores(&options, &[(0, 1), (1, 1), (0, 1), (1, 1)]), vec![1 + 2, 4 + 4, 4, 4]);
+    }
+
+    fn slow_center() -> (Graph, PlanningOptions) {
+        let graph = graph().with_entry_costs(&Graph::from_rows(&[vec![1, 1, 1], vec![1, 3, 1], vec![1, 1, 1]])).unwrap();
+        (graph, PlanningOptions { move_costs: crate::MoveCosts { straight: 1, diagonal: 2 }, ..PlanningOptions::new(10, 1) })
+    }
+
+    #[test]
+    fn test_moves_take_time() {
+        let (graph, options) = slow_center();
+        let mut simulation = Simulation::new(&graph, &options);
+
+        let steps: Vec<(u32, u64)> = [(0, 0), (0, 1), (1, 1), (2, 2)].into_iter().map(|u| simulation.step(u).unwrap()).map(|s| (s.step, s.score)).collect();
+
+        // The center takes 3 timesteps to enter and the diagonal move 2, the cells recover meanwhile
+        assert_eq!(steps, vec![(1, 0), (2, 1 + 1), (5, 4 + 4), (7, 7 + 6)]);
+        assert_eq!(simulation.timestep(), 7);
+        assert_eq!(simulation.time_since_visit((0, 1)), Some(5));
+    }
+
+    #[test]
+    fn test_moves_past_horizon() {
+        let (graph, options) = slow_center();
+        let options = PlanningOptions { max_timesteps: 4, ..options };
+        let mut simulation = Simulation::new(&graph, &options);
+
+        simulation.step((0, 0)).unwrap();
+        simulation.step((0, 1)).unwrap();
+
+        assert_eq!(simulation.neighbors(), vec![(0, 0), (0, 2), (1, 0), (1, 2)]);
+        assert_eq!(simulation.step((1, 1)), Err(VerifyError::ExceedsHorizon { step: 3, max_timesteps: 4 }));
+    }
+
+    #[test]
+    fn test_footprint_collects_square() {
+        let options = PlanningOptions { footprint: crate::Footprint::Square { radius: 1, weighted: false }, ..PlanningOptions::new(3, 1) };
+
+        // The second step collects the cells around (0, 0) only as far as they recovered since the first
+        assert_eq!(scores(&options, &[(0, 0), (1, 1), (2, 2)]), vec![1 + 1 + 4, 4 + 4 + 2 + 6 + 8, 0]);
+    }
+
+    #[test]
+    fn test_weighted_footprint() {
+        let graph = graph();
+        let options = PlanningOptions { footprint: crate::Footprint::Disk { radius: 1.0, weighted: true }, ..PlanningOptions::new(3, 0) };
+        let mut simulation = Simulation::new(&graph, &options);
+
+        // Half of the score of the neighbors is collected, rounded down, so the 1s collect nothing, and they are reset
+        assert_eq!(simulation.step((1, 1)).unwrap().score, 4 + 1 + 2);
+        assert_eq!(simulation.score_grid(), vec![vec![0, 0, 3], vec![0, 0, 0], vec![1, 0, 7]]);
+        assert_eq!(crate::Footprint::Disk { radius: 1.5, weighted: false }.offsets().len(), 9);
+    }
+
+    #[test]
+    fn test_partial_harvest() {
+        let options = PlanningOptions { harvest: crate::Harvest::Fraction { fraction: 0.5 }, ..PlanningOptions::new(3, 0) };
+        assert_eq!(scores(&options, &[(1, 1), (2, 2), (1, 1)]), vec![2, 3, 1]);
+
+        // What is left of the center recovers until the agent is back
+        let options = PlanningOptions { harvest: crate::Harvest::Limit { amount: 3 }, ..PlanningOptions::new(3, 1) };
+        assert_eq!(scores(&options, &[(1, 1), (2, 2), (1, 1)]), vec![3, 3, 1 + 1]);
+    }
+
+    #[test]
+    fn test_harvest_over_steps() {
+        let graph = graph();
+        let options = PlanningOptions { harvest: crate::Harvest::Steps { steps: 3 }, ..PlanningOptions::new(5, 0) };
+        let mut simulation = Simulation::new(&graph, &options);
+
+        simulation.step((2, 2)).unwrap();
+        assert_eq!(simulation.neighbors(), vec![(1, 1), (1, 2), (2, 1), (2, 2)]);
+
+        // The 7 takes three steps in a row, and the next cell starts over with a third of its 4
+        assert_eq!(simulation.step((2, 2)).unwrap().score, 2);
+        assert_eq!(simulation.step((2, 2)).unwrap().score, 3);
+        assert_eq!(simulation.path().iter().map(|s| s.score).collect::<Vec<_>>(), vec![2, 2, 3]);
+        assert_eq!(simulation.step((1, 1)).unwrap().score, 1);
+        assert_eq!(simulation.cell_score_at((2, 2)), 0);
+
+        let options = PlanningOptions::new(5, 0);
+        let mut simulation = Simulation::new(&graph, &options);
+        simulation.step((2, 2)).unwrap();
+        assert_eq!(simulation.step((2, 2)), Err(VerifyError::NotAdjacent { step: 2, from: (2, 2), to: (2, 2) }));
+    }
+}
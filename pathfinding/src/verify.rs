@@ -0,0 +1,164 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::{Graph, PathfindingResult, PlanningOptions, Position, Score, Simulation};
+
+/// The reason a move was rejected by [`Graph::verify_path`] or [`Simulation::step`], always
+/// describing the first invalid step by its number in the path, counting from 1, or the reason
+/// a whole path was rejected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerifyError {
+    /// The node of the step lies outside of the graph.
+    OutOfBounds { step: u32, node: Position },
+    /// The node of the step is an obstacle.
+    Obstacle { step: u32, node: Position },
+    /// The node of the step is blocked on the timestep it is entered, see [`Blockage`](crate::Blockage).
+    Blocked { step: u32, node: Position },
+    /// The node of the step can't be reached from the previous node in a single move.
+    NotAdjacent { step: u32, from: Position, to: Position },
+    /// The step ends after the maximum number of timesteps.
+    ExceedsHorizon { step: u32, max_timesteps: u32 },
+    /// The cumulative score of the path doesn't fit in a `u64` after the step.
+    ScoreOverflow { step: u32 },
+    /// The battery doesn't hold the energy the move into the node of the step uses.
+    OutOfEnergy { step: u32, node: Position },
+    /// The node of the step was entered again more often than the [`Revisits`](crate::Revisits) allow.
+    RevisitLimit { step: u32, node: Position },
+    /// The node of the step is entered again before its [`Revisits`](crate::Revisits) cooldown is over.
+    Cooldown { step: u32, node: Position },
+    /// The path missed the required [`TimeWindow`](crate::TimeWindow) of these nodes, row by row.
+    MissedWindows { nodes: Vec<Position> },
+    /// The path didn't pass the waypoint with this number, counting from 1, after the waypoints
+    /// before it.
+    MissedWaypoint { waypoint: usize, node: Position }
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerifyError::OutOfBounds { step, node } => write!(f, "step {step}: {node:?} is outside of the grid"),
+            VerifyError::Obstacle { step, node } => write!(f, "step {step}: {node:?} is an obstacle"),
+            VerifyError::Blocked { step, node } => write!(f, "step {step}: {node:?} is blocked at that time"),
+            VerifyError::NotAdjacent { step, from, to } => write!(f, "step {step}: {to:?} is not adjacent to {from:?}"),
+            VerifyError::ExceedsHorizon { step, max_timesteps } => write!(f, "step {step}: exceeds the maximum of {max_timesteps} timesteps"),
+            VerifyError::ScoreOverflow { step } => write!(f, "step {step}: the total score overflows"),
+            VerifyError::OutOfEnergy { step, node } => write!(f, "step {step}: not enough energy left to enter {node:?}"),
+            VerifyError::RevisitLimit { step, node } => write!(f, "step {step}: {node:?} was entered too often"),
+            VerifyError::Cooldown { step, node } => write!(f, "step {step}: {node:?} is entered again too soon"),
+            VerifyError::MissedWindows { nodes } => {
+                let nodes: Vec<String> = nodes.iter().map(|node| format!("{node:?}")).collect();
+                write!(f, "missed the required time windows of {}", nodes.join(", "))
+            }
+            VerifyError::MissedWaypoint { waypoint, node } => write!(f, "missed waypoint {waypoint} at {node:?}")
+        }
+    }
+}
+
+impl Error for VerifyError {}
+
+impl<S: Score> Graph<S> {
+    /// Scores a path which was not necessarily found by one of the planners, like a hand-drawn route
+    /// or the output of another tool, using the same rules as the planners.
+    ///
+    /// Every position is checked to be inside the graph, not an obstacle, not blocked when it is
+    /// entered and adjacent to the previous position under the neighborhood of the options, to be
+    /// reachable with the energy left when the options have an energy budget, and to be entered
+    /// again only as the revisit limits allow. The first step which breaks one of these rules is
+    /// returned as an error. A path which is valid step by step but misses a required
+    /// [`TimeWindow`](crate::TimeWindow) is rejected with every window it missed, see
+    /// [`Graph::missed_windows`] for the windows which are not required. A path which doesn't pass
+    /// the waypoints of the options in order is rejected with the first waypoint it missed.
+    pub fn verify_path(&self, positions: &[Position], options: &PlanningOptions) -> Result<PathfindingResult<S::Total>, VerifyError> {
+        let mut simulation = Simulation::new(self, options);
+
+        for &node in positions {
+            simulation.step(node)?;
+        }
+
+        let reached = simulation.reached_waypoints();
+        if let Some(&node) = options.waypoints.get(reached) {
+            return Err(VerifyError::MissedWaypoint { waypoint: reached + 1, node });
+        }
+
+        let missed: Vec<Position> = simulation.missed_windows().into_iter().filter(|(_, window)| window.required).map(|(node, _)| node).collect();
+        if !missed.is_empty() {
+            return Err(VerifyError::MissedWindows { nodes: missed });
+        }
+
+        Ok(simulation.into_result())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn graph() -> Graph {
+        Graph::from_bytes(b"0 1 3\n1 4 3\n1 5 7".to_vec())
+    }
+
+    #[test]
+    fn test_verify_matches_bfs() {
+        let graph = graph();
+        let options = PlanningOptions::new(12, 2);
+        let planned = graph.path_planning_bfs_observed((0, 0), &options, &mut crate::NoopObserver);
+        let positions: Vec<Position> = planned.path.iter().map(|s| s.node).collect();
+        let verified = graph.verify_path(&positions, &options).unwrap();
+
+        assert_eq!(verified.path.iter().map(|s| s.score).collect::<Vec<_>>(), planned.path.iter().map(|s| s.score).collect::<Vec<_>>());
+        assert_eq!(verified.score(), planned.score());
+    }
+
+    #[test]
+    fn test_verify_revisit_recovers() {
+        let verified = graph().verify_path(&[(2, 2), (1, 1), (2, 2)], &PlanningOptions::new(3, 1)).unwrap();
+        assert_eq!(verified.path.iter().map(|s| s.score).collect::<Vec<_>>(), vec![7, 5, 1]);
+    }
+
+    #[test]
+    fn test_verify_out_of_bounds() {
+        let error = graph().verify_path(&[(1, 1), (2, 2), (3, 3)], &PlanningOptions::new(5, 1)).unwrap_err();
+        assert_eq!(error, VerifyError::OutOfBounds { step: 3, node: (3, 3) });
+    }
+
+    #[test]
+    fn test_verify_not_adjacent() {
+        let error = graph().verify_path(&[(0, 0), (2, 2)], &PlanningOptions::new(5, 1)).unwrap_err();
+        assert_eq!(error, VerifyError::NotAdjacent { step: 2, from: (0, 0), to: (2, 2) });
+    }
+
+    #[test]
+    fn test_verify_diagonal_in_von_neumann() {
+        let options = PlanningOptions { neighborhood: crate::Neighborhood::VonNeumann, ..PlanningOptions::new(5, 1) };
+        let error = graph().verify_path(&[(0, 0), (1, 1)], &options).unwrap_err();
+        assert_eq!(error, VerifyError::NotAdjacent { step: 2, from: (0, 0), to: (1, 1) });
+    }
+
+    #[test]
+    fn test_verify_obstacle() {
+        let mut graph = graph();
+        graph.add_obstacle((1, 1));
+        let error = graph.verify_path(&[(0, 0), (1, 1)], &PlanningOptions::new(5, 1)).unwrap_err();
+        assert_eq!(error, VerifyError::Obstacle { step: 2, node: (1, 1) });
+    }
+
+    #[test]
+    fn test_verify_exceeds_horizon() {
+        let error = graph().verify_path(&[(0, 0), (0, 1), (0, 2)], &PlanningOptions::new(2, 1)).unwrap_err();
+        assert_eq!(error, VerifyError::ExceedsHorizon { step: 3, max_timesteps: 2 });
+    }
+
+    #[test]
+    fn test_verify_score_overflow() {
+        let graph = Graph::from_rows(&[vec![0; 100_000]]);
+        let positions: Vec<Position> = (0..100_000).map(|j| (0, j)).collect();
+        let options = PlanningOptions::new(100_000, u32::MAX);
+
+        // Step k enters a cell which recovered k - 1 times, so the total after k steps is rate * k * (k - 1) / 2
+        let rate = u32::MAX as u128;
+        let step = (1..).find(|&k: &u128| rate * k * (k - 1) / 2 > u64::MAX as u128).unwrap() as u32;
+
+        let error = graph.verify_path(&positions, &options).unwrap_err();
+        assert_eq!(error, VerifyError::ScoreOverflow { step });
+    }
+}
@@ -0,0 +1,116 @@
+use crate::costs::timesteps_to;
+use crate::{Graph, PlanningOptions, Position, Score, Simulation};
+
+/// The fewest timesteps it takes to reach every waypoint of the options from every cell, and to
+/// pass the waypoints after it in order, which the planners use to pass the waypoints in time.
+pub(crate) struct Route {
+    /// Every waypoint with the timesteps to reach it from every cell.
+    legs: Vec<(Position, Vec<u64>)>,
+    /// The timesteps from every waypoint through the waypoints after it.
+    tails: Vec<u64>
+}
+
+impl Route {
+    /// Finds the timesteps with a search outwards from every waypoint, `None` without waypoints.
+    pub(crate) fn new<S: Score>(graph: &Graph<S>, options: &PlanningOptions) -> Option<Self> {
+        if options.waypoints.is_empty() {
+            return None;
+        }
+
+        let legs: Vec<(Position, Vec<u64>)> = options.waypoints.iter().map(|&u| (u, timesteps_to(graph, &[u], options))).collect();
+        let mut tails = vec![0u64; legs.len()];
+        for k in (0..legs.len() - 1).rev() {
+            let (u, _) = legs[k];
+            let between = if graph.contains(u) { legs[k + 1].1[graph.index(u)] } else { u64::MAX };
+            tails[k] = between.saturating_add(tails[k + 1]);
+        }
+
+        Some(Route { legs, tails })
+    }
+
+    /// Returns the fewest timesteps it takes from the cell through the waypoints from the `k`th on.
+    fn remaining(&self, graph: &Graph<impl Score>, u: Position, k: usize) -> u64 {
+        match self.legs.get(k) {
+            Some((_, timesteps)) => timesteps[graph.index(u)].saturating_add(self.tails[k]),
+            None => 0
+        }
+    }
+
+    /// Returns whether the agent can still pass the waypoints it didn't pass yet before the time is up.
+    pub(crate) fn can_reach<S: Score>(&self, simulation: &Simulation<'_, S>) -> bool {
+        let Some(u) = simulation.position() else {
+            return true;
+        };
+        let remaining = self.remaining(simulation.graph(), u, simulation.reached_waypoints());
+        (simulation.timestep() as u64).saturating_add(remaining) <= simulation.max_steps() as u64
+    }
+
+    /// Returns whether the agent can still pass the waypoints it didn't pass yet before the time is
+    /// up after entering the cell.
+    pub(crate) fn keeps<S: Score>(&self, simulation: &Simulation<'_, S>, u: Position) -> bool {
+        let mut k = simulation.reached_waypoints();
+        if self.legs.get(k).is_some_and(|&(v, _)| v == u) {
+            k += 1;
+        }
+        (simulation.arrival(u) as u64).saturating_add(self.remaining(simulation.graph(), u, k)) <= simulation.max_steps() as u64
+    }
+
+    /// Returns the neighbor on a quickest way to the next waypoint, `None` when every waypoint was
+    /// passed or the next one can't be reached.
+    pub(crate) fn towards<S: Score>(&self, simulation: &Simulation<'_, S>) -> Option<Position> {
+        let (_, timesteps) = self.legs.get(simulation.reached_waypoints())?;
+        let graph = simulation.graph();
+
+        simulation.neighbors()
+            .into_iter()
+            .filter(|&u| timesteps[graph.index(u)] < u64::MAX)
+            .min_by_key(|&u| (simulation.arrival(u) as u64).saturating_add(timesteps[graph.index(u)]))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{NoopObserver, Planner, VerifyError};
+
+    fn graph() -> Graph {
+        Graph::from_bytes(b"0 1 3\n1 4 3\n1 5 7".to_vec())
+    }
+
+    #[test]
+    fn test_missed_waypoint() {
+        let options = PlanningOptions { waypoints: vec![(0, 2), (2, 0)], ..PlanningOptions::new(5, 1) };
+
+        // The waypoints have to be passed in order
+        let error = graph().verify_path(&[(1, 1), (2, 0), (1, 1), (0, 2)], &options).unwrap_err();
+        assert_eq!(error, VerifyError::MissedWaypoint { waypoint: 2, node: (2, 0) });
+        assert!(graph().verify_path(&[(1, 1), (0, 2), (1, 1), (2, 0)], &options).is_ok());
+    }
+
+    #[test]
+    fn test_route() {
+        let graph = graph();
+        let options = PlanningOptions { waypoints: vec![(0, 2), (2, 0)], ..PlanningOptions::new(5, 1) };
+        let route = Route::new(&graph, &options).unwrap();
+        let mut simulation = Simulation::new(&graph, &options);
+
+        simulation.step((1, 0)).unwrap();
+        assert_eq!(route.tails, vec![2, 0]);
+        assert!(route.can_reach(&simulation));
+        assert!(route.keeps(&simulation, (0, 1)));
+        assert!(!route.keeps(&simulation, (2, 0)));
+        assert_eq!(route.towards(&simulation), Some((0, 1)));
+    }
+
+    #[test]
+    fn test_planners_pass_waypoints() {
+        let graph = graph();
+        let options = PlanningOptions { waypoints: vec![(0, 0), (2, 0)], ..PlanningOptions::new(5, 1) };
+
+        for planner in [Planner::BestFirstSearch, Planner::DepthFirstSearch] {
+            let result = planner.plan(&graph, (2, 2), &options, &mut NoopObserver);
+            let positions: Vec<Position> = result.path.iter().map(|s| s.node).collect();
+            assert!(graph.verify_path(&positions, &options).is_ok(), "{planner} missed a waypoint in {positions:?}");
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use std::fmt;
+
+use crate::costs::timesteps_to;
+use crate::{Graph, PlanningOptions, Position, Score, Simulation};
+
+/// The timesteps during which a cell is worth collecting, like an inspection point which has to
+/// be seen between two times, see [`Graph::add_window`].
+///
+/// A window is met when the cell is visited during it, by entering it or with the
+/// [`Footprint`](crate::Footprint) around the agent, even when a full hopper leaves its score.
+/// Outside of its window the cell scores nothing, not even a penalty, and is left alone. A path
+/// which misses a `required` window is invalid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TimeWindow {
+    /// The first timestep of the window.
+    pub open: u32,
+    /// The last timestep of the window.
+    pub close: u32,
+    /// Whether a path has to meet the window.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub required: bool
+}
+
+impl TimeWindow {
+    /// Returns whether the window is open on the given timestep.
+    pub fn contains(&self, timestep: u32) -> bool {
+        (self.open..=self.close).contains(&timestep)
+    }
+}
+
+impl fmt::Display for TimeWindow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.open, self.close)
+    }
+}
+
+impl<S: Score> Graph<S> {
+    /// Mutates the existing graph to give the node at the given position a time window, replacing
+    /// the window it had.
+    pub fn add_window(&mut self, u: Position, window: TimeWindow) {
+        self.windows.insert(u, window);
+    }
+
+    /// Returns the time window of the node at the given position, `None` when it is always worth collecting.
+    pub fn window(&self, u: Position) -> Option<TimeWindow> {
+        self.windows.get(&u).copied()
+    }
+
+    /// Returns the positions with a time window together with the window, row by row.
+    pub fn windows(&self) -> Vec<(Position, TimeWindow)> {
+        let mut windows: Vec<(Position, TimeWindow)> = self.windows.iter().map(|(&u, &window)| (u, window)).collect();
+        windows.sort_unstable_by_key(|&(u, _)| u);
+        windows
+    }
+
+    /// Returns the time windows the path missed, row by row, replaying it with the options.
+    pub fn missed_windows(&self, positions: &[Position], options: &PlanningOptions) -> Vec<(Position, TimeWindow)> {
+        let mut simulation = Simulation::new(self, options);
+        for &node in positions {
+            if simulation.step(node).is_err() {
+                break;
+            }
+        }
+        simulation.missed_windows()
+    }
+}
+
+/// The fewest timesteps it takes to reach every required window from every cell, which the best
+/// first search uses to reach the windows before they close.
+pub(crate) struct Deadlines {
+    windows: Vec<(Position, TimeWindow, Vec<u64>)>
+}
+
+impl Deadlines {
+    /// Finds the timesteps with a search outwards from every required window, `None` without any.
+    pub(crate) fn new<S: Score>(graph: &Graph<S>, options: &PlanningOptions) -> Option<Self> {
+        let windows: Vec<_> = graph.windows()
+            .into_iter()
+            .filter(|(_, window)| window.required)
+            .map(|(u, window)| (u, window, timesteps_to(graph, &[u], options)))
+            .collect();
+
+        (!windows.is_empty()).then_some(Deadlines { windows })
+    }
+
+    /// Returns whether every required window which is still within reach and not met yet can
+    /// still be reached before it closes after entering the cell.
+    pub(crate) fn keeps<S: Score>(&self, simulation: &Simulation<'_, S>, u: Position) -> bool {
+        let Some(from) = simulation.position() else {
+            return true;
+        };
+        let graph = simulation.graph();
+
+        self.windows.iter().all(|(v, window, timesteps)| {
+            let reachable = (simulation.timestep() as u64).saturating_add(timesteps[graph.index(from)]) <= window.close as u64;
+            simulation.has_met(*v)
+                || !reachable
+                || (simulation.arrival(u) as u64).saturating_add(timesteps[graph.index(u)]) <= window.close as u64
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{NoopObserver, VerifyError};
+
+    fn corridor(required: bool) -> Graph {
+        let mut graph = Graph::from_rows(&[vec![1, 1, 1, 1, 5]]);
+        graph.add_window((0, 4), TimeWindow { open: 3, close: 5, required });
+        graph
+    }
+
+    #[test]
+    fn test_scores_inside_window() {
+        let graph = corridor(false);
+        let options = PlanningOptions::new(10, 0);
+
+        // The 5 is reached on timestep 5, before that it would be worth nothing
+        let path = graph.verify_path(&[(0, 0), (0, 1), (0, 2), (0, 3), (0, 4)], &options).unwrap();
+        assert_eq!(path.score(), 4 + 5);
+
+        let path = [(0, 1), (0, 2), (0, 3), (0, 4), (0, 3), (0, 2)];
+        assert_eq!(graph.verify_path(&path[..4], &options).unwrap().score(), 3 + 5);
+        assert_eq!(graph.missed_windows(&path[..2], &options), vec![((0, 4), TimeWindow { open: 3, close: 5, required: false })]);
+    }
+
+    #[test]
+    fn test_required_window() {
+        let graph = corridor(true);
+        let options = PlanningOptions::new(10, 0);
+
+        // Entering the 5 on timestep 2 is too early
+        let error = graph.verify_path(&[(0, 3), (0, 4), (0, 3)], &options).unwrap_err();
+        assert_eq!(error, VerifyError::MissedWindows { nodes: vec![(0, 4)] });
+        assert!(graph.verify_path(&[(0, 1), (0, 2), (0, 3), (0, 4)], &options).is_ok());
+    }
+
+    #[test]
+    fn test_penalty_inside_window() {
+        let mut graph: Graph<i64> = Graph::parse_values("0 0 0 0 -5").unwrap();
+        graph.add_window((0, 4), TimeWindow { open: 5, close: 6, required: false });
+        let options = PlanningOptions::new(10, 0);
+
+        // Like any other score, the cost only counts inside the window
+        assert_eq!(graph.verify_path(&[(0, 3), (0, 4)], &options).unwrap().score(), 0);
+        assert_eq!(graph.verify_path(&[(0, 0), (0, 1), (0, 2), (0, 3), (0, 4)], &options).unwrap().score(), -5);
+    }
+
+    #[test]
+    fn test_planners_meet_required_window() {
+        let graph = corridor(true);
+        let options = PlanningOptions { neighborhood: crate::Neighborhood::VonNeumann, ..PlanningOptions::new(8, 1) };
+
+        for planner in [crate::Planner::BestFirstSearch, crate::Planner::DepthFirstSearch] {
+            let result = planner.plan(&graph, (0, 0), &options, &mut NoopObserver);
+            let positions: Vec<Position> = result.path.iter().map(|s| s.node).collect();
+            assert!(graph.verify_path(&positions, &options).is_ok(), "{planner} missed the window");
+        }
+    }
+}